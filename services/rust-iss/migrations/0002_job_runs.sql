@@ -0,0 +1,14 @@
+-- История запусков фоновых задач
+CREATE TABLE IF NOT EXISTS job_runs (
+    id BIGSERIAL PRIMARY KEY,
+    job_name TEXT NOT NULL,
+    trigger TEXT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at TIMESTAMPTZ,
+    outcome TEXT NOT NULL,
+    error TEXT,
+    rows_written BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS ix_job_runs_name
+    ON job_runs(job_name, started_at DESC);
@@ -0,0 +1,33 @@
+-- Типизированные колонки позиции МКС
+ALTER TABLE iss_fetch_log
+    ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS altitude DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS velocity DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS visibility TEXT,
+    ADD COLUMN IF NOT EXISTS footprint DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS position_at TIMESTAMPTZ;
+
+-- Заполнение колонок для строк, сохранённых до их появления
+-- (pg_temp-функция живёт до конца сессии, миграция идёт в одной транзакции)
+CREATE OR REPLACE FUNCTION pg_temp.jsonb_num(v JSONB) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE
+        WHEN jsonb_typeof(v) = 'number' THEN (v #>> '{}')::DOUBLE PRECISION
+        WHEN jsonb_typeof(v) = 'string'
+             AND (v #>> '{}') ~ '^\s*-?[0-9]+(\.[0-9]+)?([eE][-+]?[0-9]+)?\s*$'
+            THEN (v #>> '{}')::DOUBLE PRECISION
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+UPDATE iss_fetch_log SET
+    latitude = pg_temp.jsonb_num(payload->'latitude'),
+    longitude = pg_temp.jsonb_num(payload->'longitude'),
+    altitude = pg_temp.jsonb_num(payload->'altitude'),
+    velocity = pg_temp.jsonb_num(payload->'velocity'),
+    visibility = payload->>'visibility',
+    footprint = pg_temp.jsonb_num(payload->'footprint'),
+    position_at = CASE WHEN jsonb_typeof(payload->'timestamp') = 'number'
+                       THEN to_timestamp((payload->>'timestamp')::DOUBLE PRECISION) END
+ WHERE latitude IS NULL
+   AND pg_temp.jsonb_num(payload->'latitude') IS NOT NULL
+   AND pg_temp.jsonb_num(payload->'longitude') IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Орбитальные элементы МКС (TLE)
+CREATE TABLE IF NOT EXISTS tle_sets (
+    id BIGSERIAL PRIMARY KEY,
+    norad_id INTEGER NOT NULL,
+    name TEXT,
+    line1 TEXT NOT NULL,
+    line2 TEXT NOT NULL,
+    epoch TIMESTAMPTZ NOT NULL,
+    source TEXT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (norad_id, epoch)
+);
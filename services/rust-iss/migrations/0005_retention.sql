@@ -0,0 +1,28 @@
+-- Почасовые агрегаты позиций МКС: сюда сворачиваются сырые строки
+-- iss_fetch_log старше ISS_RAW_RETENTION
+CREATE TABLE IF NOT EXISTS iss_position_hourly (
+    hour TIMESTAMPTZ PRIMARY KEY,
+    samples INTEGER NOT NULL,
+    first_at TIMESTAMPTZ NOT NULL,
+    last_at TIMESTAMPTZ NOT NULL,
+    min_latitude DOUBLE PRECISION,
+    max_latitude DOUBLE PRECISION,
+    avg_altitude DOUBLE PRECISION,
+    min_altitude DOUBLE PRECISION,
+    max_altitude DOUBLE PRECISION,
+    avg_velocity DOUBLE PRECISION
+);
+
+-- Отчёт политики хранения: что и сколько удалено за запуск
+CREATE TABLE IF NOT EXISTS retention_log (
+    id BIGSERIAL PRIMARY KEY,
+    ran_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    target TEXT NOT NULL,
+    source TEXT,
+    policy TEXT NOT NULL,
+    aggregated BIGINT NOT NULL DEFAULT 0,
+    deleted BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS ix_retention_log_ran_at
+    ON retention_log(ran_at DESC);
@@ -0,0 +1,20 @@
+-- Хэш содержимого space_cache: если ответ источника не изменился, новая строка
+-- не пишется, а у последней обновляется last_seen_at. Хэш считается от
+-- канонического текста jsonb, поэтому порядок ключей в ответе не важен
+ALTER TABLE space_cache
+    ADD COLUMN IF NOT EXISTS payload_hash TEXT,
+    ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ;
+
+UPDATE space_cache
+   SET payload_hash = encode(sha256(convert_to(payload::text, 'UTF8')), 'hex'),
+       last_seen_at = fetched_at
+ WHERE payload_hash IS NULL;
+
+ALTER TABLE space_cache
+    ALTER COLUMN payload_hash SET NOT NULL,
+    ALTER COLUMN last_seen_at SET NOT NULL,
+    ALTER COLUMN last_seen_at SET DEFAULT now();
+
+-- Сколько данных задача получила, но не записала, так как они не изменились
+ALTER TABLE job_runs
+    ADD COLUMN IF NOT EXISTS rows_unchanged BIGINT;
@@ -0,0 +1,27 @@
+-- Околоземные объекты из ленты NeoWs (источник neo), по одному на id NASA
+CREATE TABLE IF NOT EXISTS neo_objects (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    nasa_jpl_url TEXT,
+    absolute_magnitude DOUBLE PRECISION,
+    diameter_min_km DOUBLE PRECISION,
+    diameter_max_km DOUBLE PRECISION,
+    hazardous BOOLEAN NOT NULL DEFAULT false,
+    sentry BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Сближения объектов; одно сближение однозначно задаётся объектом и моментом
+CREATE TABLE IF NOT EXISTS neo_close_approaches (
+    id BIGSERIAL PRIMARY KEY,
+    neo_id TEXT NOT NULL REFERENCES neo_objects(id) ON DELETE CASCADE,
+    approach_at TIMESTAMPTZ NOT NULL,
+    miss_distance_km DOUBLE PRECISION,
+    relative_velocity_kms DOUBLE PRECISION,
+    orbiting_body TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (neo_id, approach_at)
+);
+
+CREATE INDEX IF NOT EXISTS ix_neo_close_approaches_at
+    ON neo_close_approaches(approach_at);
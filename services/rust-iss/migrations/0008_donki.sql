@@ -0,0 +1,45 @@
+-- Вспышки DONKI FLR, по одной на flrID
+CREATE TABLE IF NOT EXISTS flares (
+    id TEXT PRIMARY KEY,
+    class_type TEXT,
+    -- Пиковый поток в Вт/м2 по классу (M1.2 -> 1.2e-5), для фильтра по минимальному классу
+    class_flux DOUBLE PRECISION,
+    begin_at TIMESTAMPTZ,
+    peak_at TIMESTAMPTZ,
+    end_at TIMESTAMPTZ,
+    source_location TEXT,
+    active_region INTEGER,
+    link TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS ix_flares_peak_at ON flares(peak_at);
+
+-- Корональные выбросы DONKI CME, по одному на activityID.
+-- Параметры берутся из наиболее точного анализа (isMostAccurate)
+CREATE TABLE IF NOT EXISTS cmes (
+    id TEXT PRIMARY KEY,
+    start_at TIMESTAMPTZ,
+    speed_kms DOUBLE PRECISION,
+    half_angle_deg DOUBLE PRECISION,
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    cme_type TEXT,
+    source_location TEXT,
+    active_region INTEGER,
+    note TEXT,
+    link TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS ix_cmes_start_at ON cmes(start_at);
+
+-- linkedEvents из ответов DONKI: связь от события к любому другому
+-- событию DONKI (FLR, CME, SEP, GST, ...), даже если его нет в таблицах
+CREATE TABLE IF NOT EXISTS donki_links (
+    from_id TEXT NOT NULL,
+    to_id TEXT NOT NULL,
+    PRIMARY KEY (from_id, to_id)
+);
+
+CREATE INDEX IF NOT EXISTS ix_donki_links_to ON donki_links(to_id);
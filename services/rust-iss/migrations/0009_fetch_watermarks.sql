@@ -0,0 +1,7 @@
+-- Последняя дата, по которую источник с датированными окнами (neo, flr, cme)
+-- успешно загружен. Плановая загрузка продолжает с этой даты, а не с фиксированного окна
+CREATE TABLE IF NOT EXISTS fetch_watermarks (
+    source TEXT PRIMARY KEY,
+    covered_to DATE NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
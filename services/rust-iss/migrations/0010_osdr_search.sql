@@ -0,0 +1,21 @@
+-- Полнотекстовый поиск по OSDR: заголовок (вес A), описание (B),
+-- организм, миссия, проект и типы исследований из raw (C)
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english'::regconfig, coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('english'::regconfig, coalesce(raw->>'description', '')), 'B') ||
+        setweight(jsonb_to_tsvector(
+            'english'::regconfig,
+            jsonb_path_query_array(raw, '$.organism') ||
+                jsonb_path_query_array(raw, '$.mission') ||
+                jsonb_path_query_array(raw, '$.project') ||
+                jsonb_path_query_array(raw, '$.assay_types'),
+            '["string"]'
+        ), 'C')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS ix_osdr_items_search
+    ON osdr_items USING GIN (search_vector);
+
+CREATE INDEX IF NOT EXISTS ix_osdr_items_updated_at
+    ON osdr_items(updated_at);
@@ -0,0 +1,20 @@
+-- Каждая отличающаяся ревизия raw датасета OSDR с разницей к предыдущей
+-- (diff пуст у первой версии). Версии нумеруются с 1 внутри датасета
+CREATE TABLE IF NOT EXISTS osdr_item_versions (
+    id BIGSERIAL PRIMARY KEY,
+    item_id BIGINT NOT NULL REFERENCES osdr_items(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    title TEXT,
+    status TEXT,
+    updated_at TIMESTAMPTZ,
+    raw JSONB NOT NULL,
+    diff JSONB,
+    UNIQUE (item_id, version)
+);
+
+-- Текущее состояние уже загруженных датасетов - их первая версия
+INSERT INTO osdr_item_versions (item_id, version, recorded_at, title, status, updated_at, raw)
+SELECT id, 1, inserted_at, title, status, updated_at, raw
+FROM osdr_items
+ON CONFLICT (item_id, version) DO NOTHING;
@@ -0,0 +1,2 @@
+-- Когда датасет впервые не оказался в выдаче OSDR; сбрасывается, если он вернулся
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS missing_since TIMESTAMPTZ;
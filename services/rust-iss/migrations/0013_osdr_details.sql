@@ -0,0 +1,3 @@
+-- Когда метаданные датасета последний раз запрашивались у OSDR; по нему
+-- синхронизация решает, брать ли сохранённый raw или идти за свежими
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS details_fetched_at TIMESTAMPTZ;
@@ -0,0 +1,16 @@
+-- Средние высоты и скорости считаются только по строкам, где значение есть,
+-- поэтому при слиянии часов их нужно взвешивать своими счётчиками, а не samples.
+-- Для уже свёрнутых часов точное число не восстановить - берём samples
+ALTER TABLE iss_position_hourly ADD COLUMN IF NOT EXISTS altitude_samples INTEGER;
+ALTER TABLE iss_position_hourly ADD COLUMN IF NOT EXISTS velocity_samples INTEGER;
+
+UPDATE iss_position_hourly
+SET altitude_samples = CASE WHEN avg_altitude IS NULL THEN 0 ELSE samples END
+WHERE altitude_samples IS NULL;
+
+UPDATE iss_position_hourly
+SET velocity_samples = CASE WHEN avg_velocity IS NULL THEN 0 ELSE samples END
+WHERE velocity_samples IS NULL;
+
+ALTER TABLE iss_position_hourly ALTER COLUMN altitude_samples SET NOT NULL;
+ALTER TABLE iss_position_hourly ALTER COLUMN velocity_samples SET NOT NULL;
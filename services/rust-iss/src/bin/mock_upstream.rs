@@ -0,0 +1,182 @@
+// Заглушка внешних API для запуска без сети: отдаёт записанные ответы из
+// fixtures/upstream по тем же путям, что и настоящие сервисы.
+//   cargo run --features mock-upstream --bin mock_upstream -- --listen 127.0.0.1:8090
+// rust_iss направляется на неё через NASA_API_BASE, SPACEX_API_BASE, WHERE_ISS_URL,
+// TLE_URL и NASA_API_URL. Для api.nasa.gov считается квота по api_key с заголовками
+// X-RateLimit-*, после её исчерпания - 429, как у настоящего API. Листинг OSDR
+// постраничный (--osdr-page-size) и отдаёт заглушки со ссылками на метаданные и файлы
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use clap::Parser;
+use tracing::info;
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
+
+const APOD: &str = include_str!("../../fixtures/upstream/apod.json");
+const NEO_FEED: &str = include_str!("../../fixtures/upstream/neo_feed.json");
+const DONKI_FLR: &str = include_str!("../../fixtures/upstream/donki_flr.json");
+const DONKI_CME: &str = include_str!("../../fixtures/upstream/donki_cme.json");
+const SPACEX_NEXT: &str = include_str!("../../fixtures/upstream/spacex_next.json");
+const ISS: &str = include_str!("../../fixtures/upstream/iss.json");
+const OSDR_DATASETS: &str = include_str!("../../fixtures/upstream/osdr_datasets.json");
+const OSDR_FILES: &str = include_str!("../../fixtures/upstream/osdr_files.json");
+const ISS_TLE: &str = include_str!("../../fixtures/upstream/iss_tle.txt");
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+#[derive(Parser)]
+#[command(name = "mock_upstream", about = "Recorded NASA, SpaceX, ISS and TLE responses for offline runs")]
+struct Args {
+    #[arg(long, default_value = "127.0.0.1:8090")]
+    listen: SocketAddr,
+    /// Requests per api_key before api.nasa.gov paths answer 429
+    #[arg(long, default_value_t = 1000)]
+    quota: i64,
+    /// Seconds sent in Retry-After with 429
+    #[arg(long, default_value_t = 60)]
+    retry_after: u64,
+    /// Datasets per OSDR listing page
+    #[arg(long, default_value_t = 2)]
+    osdr_page_size: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct MockState {
+    quota: i64,
+    retry_after: u64,
+    osdr_page_size: usize,
+    used: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let subscriber = FmtSubscriber::builder()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .finish();
+    let _ = tracing::subscriber::set_global_default(subscriber);
+
+    let args = Args::parse();
+    let app = router(MockState::new(args.quota, args.retry_after, args.osdr_page_size));
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    info!("mock upstream listening on http://{}", args.listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+impl MockState {
+    pub(crate) fn new(quota: i64, retry_after: u64, osdr_page_size: usize) -> Self {
+        Self {
+            quota,
+            retry_after,
+            osdr_page_size: osdr_page_size.max(1),
+            used: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+// Вынесено из main для tests/mock_upstream.rs: там заглушка поднимается на свободном порту
+pub(crate) fn router(state: MockState) -> Router {
+    Router::new()
+        .route("/planetary/apod", get(|s, q| nasa(s, q, APOD)))
+        .route("/neo/rest/v1/feed", get(|s, q| nasa(s, q, NEO_FEED)))
+        .route("/DONKI/FLR", get(|s, q| nasa(s, q, DONKI_FLR)))
+        .route("/DONKI/CME", get(|s, q| nasa(s, q, DONKI_CME)))
+        .route("/v4/launches/next", get(|| async { json(SPACEX_NEXT) }))
+        .route("/v1/satellites/25544", get(iss))
+        .route("/biodata/api/v2/datasets/", get(osdr_listing))
+        .route("/biodata/api/v2/dataset/:id/", get(osdr_dataset))
+        .route("/biodata/api/v2/dataset/:id/files/", get(osdr_files))
+        .route("/NORAD/elements/gp.php", get(|| async { ISS_TLE }))
+        .with_state(state)
+}
+
+async fn nasa(
+    State(state): State<MockState>,
+    Query(q): Query<HashMap<String, String>>,
+    body: &'static str,
+) -> Response {
+    let key = q.get("api_key").cloned().unwrap_or_default();
+    let used = {
+        let mut used = state.used.lock().unwrap_or_else(|e| e.into_inner());
+        let count = used.entry(key.clone()).or_insert(0);
+        *count += 1;
+        *count
+    };
+    let remaining = (state.quota - used).max(0);
+    let shown: String = key.chars().take(4).collect();
+    info!("api.nasa.gov request with key '{}***': {} of {}", shown, used, state.quota);
+
+    let limits = [
+        (RATE_LIMIT_LIMIT, state.quota.to_string()),
+        (RATE_LIMIT_REMAINING, remaining.to_string()),
+    ];
+    if used > state.quota {
+        let body = r#"{"error":{"code":"OVER_RATE_LIMIT","message":"You have exceeded your rate limit."}}"#;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            limits,
+            [(header::RETRY_AFTER, state.retry_after.to_string())],
+            json(body),
+        )
+            .into_response();
+    }
+    (limits, json(body)).into_response()
+}
+
+// Позиция та же, но с текущим временем, чтобы запись не выглядела устаревшей
+async fn iss() -> Response {
+    let mut position: serde_json::Value = serde_json::from_str(ISS).unwrap_or_default();
+    position["timestamp"] = chrono::Utc::now().timestamp().into();
+    axum::Json(position).into_response()
+}
+
+// Страница заглушек {"OSD-1": {"REST_URL": ...}} и относительная ссылка на следующую
+async fn osdr_listing(State(state): State<MockState>, Query(q): Query<HashMap<String, String>>) -> Response {
+    let datasets = osdr_datasets();
+    let page: usize = q.get("page").and_then(|p| p.parse().ok()).unwrap_or(1).max(1);
+    let start = (page - 1) * state.osdr_page_size;
+    let results: serde_json::Map<String, serde_json::Value> = datasets
+        .iter()
+        .skip(start)
+        .take(state.osdr_page_size)
+        .filter_map(|d| d["id"].as_str())
+        .map(|id| (id.to_string(), serde_json::json!({ "REST_URL": format!("/biodata/api/v2/dataset/{}/", id) })))
+        .collect();
+    let next = (start + state.osdr_page_size < datasets.len())
+        .then(|| format!("/biodata/api/v2/datasets/?format=json&page={}", page + 1));
+    axum::Json(serde_json::json!({ "results": results, "next": next, "total": datasets.len() })).into_response()
+}
+
+async fn osdr_dataset(Path(id): Path<String>) -> Response {
+    match osdr_datasets().into_iter().find(|d| d["id"] == id.as_str()) {
+        Some(mut dataset) => {
+            dataset["files"] = serde_json::json!({ "REST_URL": format!("/biodata/api/v2/dataset/{}/files/", id) });
+            axum::Json(serde_json::json!({ id: dataset })).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn osdr_files(Path(id): Path<String>) -> Response {
+    let files: serde_json::Value = serde_json::from_str(OSDR_FILES).unwrap_or_default();
+    match files.get(&id) {
+        Some(listing) => axum::Json(serde_json::json!({ id: listing })).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn osdr_datasets() -> Vec<serde_json::Value> {
+    serde_json::from_str(OSDR_DATASETS).unwrap_or_default()
+}
+
+fn json(body: &'static str) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
@@ -0,0 +1,421 @@
+// Операционная CLI-утилита: разовые синхронизации, миграции, выгрузка,
+// очистка и статистика БД. Коды выхода рассчитаны на cron и CI:
+//   0 - успех, 1 - ошибка, 2 - неверные аргументы, 3 - часть источников не обновилась
+//   (или, для `migrate --status`, есть непримененные миграции), 4 - задача уже идёт
+//   (её advisory lock держит планировщик или другой запуск)
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
+
+use rust_iss::config::Config;
+use rust_iss::domain::{ApiError, RetentionReport};
+use rust_iss::jobs::{space_jobs, IssFetchJob, OsdrSyncJob, RetentionJob, TleFetchJob};
+use rust_iss::migrate;
+use rust_iss::repo::DataTable;
+use rust_iss::scheduler::{redact_api_key, run_exclusive, Job, RunOutput, TRIGGER_CLI};
+use rust_iss::services::{ExportFormat, MaintenanceService, OsdrService, RetentionService, SpaceCacheService};
+use rust_iss::state::AppState;
+use rust_iss::validation::{parse_duration, Validator};
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_PARTIAL: u8 = 3;
+const EXIT_BUSY: u8 = 4;
+
+#[derive(Parser)]
+#[command(name = "rust_iss-admin", about = "Operational tasks for rust_iss")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply pending migrations
+    Migrate {
+        /// Only list pending migrations, exit 3 if there are any
+        #[arg(long)]
+        status: bool,
+    },
+    /// NASA OSDR datasets
+    Osdr {
+        #[command(subcommand)]
+        action: SyncAction,
+    },
+    /// APOD / NEO / DONKI / SpaceX cache
+    Space {
+        #[command(subcommand)]
+        action: SpaceAction,
+    },
+    /// ISS position
+    Iss {
+        #[command(subcommand)]
+        action: FetchAction,
+    },
+    /// ISS orbital elements
+    Tle {
+        #[command(subcommand)]
+        action: FetchAction,
+    },
+    /// Export table rows as JSON lines or CSV
+    Export {
+        table: TableArg,
+        /// space_cache source (apod, neo, flr, cme, spacex)
+        #[arg(long)]
+        source: Option<String>,
+        /// RFC 3339 timestamp, inclusive
+        #[arg(long)]
+        from: Option<String>,
+        /// RFC 3339 timestamp, exclusive
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long, value_enum, default_value_t = FormatArg::Jsonl)]
+        format: FormatArg,
+        /// Output file, stdout by default
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Delete rows older than the given age
+    Purge {
+        table: TableArg,
+        /// Age like 30d, 12h or 90m
+        #[arg(long)]
+        older_than: String,
+        /// space_cache source (apod, neo, flr, cme, spacex)
+        #[arg(long)]
+        source: Option<String>,
+        /// Only count matching rows
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Apply the retention policy (ISS_RAW_RETENTION, SPACE_CACHE_KEEP, ...)
+    Retention {
+        /// Only report what would be aggregated and deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Table sizes, latest data per source and job health
+    Stats {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncAction {
+    Sync,
+}
+
+#[derive(Subcommand)]
+enum FetchAction {
+    Fetch,
+}
+
+#[derive(Subcommand)]
+enum SpaceAction {
+    /// Refresh the given sources, all of them by default (flr and cme run together as donki)
+    Refresh { sources: Vec<String> },
+    /// Fetch a date range for neo, flr or cme in windows of up to 7 days
+    Backfill {
+        source: String,
+        /// First date, YYYY-MM-DD
+        #[arg(long)]
+        from: String,
+        /// Last date (inclusive), YYYY-MM-DD
+        #[arg(long)]
+        to: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TableArg {
+    Iss,
+    Osdr,
+    Space,
+    JobRuns,
+}
+
+impl From<TableArg> for DataTable {
+    fn from(table: TableArg) -> Self {
+        match table {
+            TableArg::Iss => DataTable::IssFetchLog,
+            TableArg::Osdr => DataTable::OsdrItems,
+            TableArg::Space => DataTable::SpaceCache,
+            TableArg::JobRuns => DataTable::JobRuns,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Jsonl,
+    Csv,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Логи в stderr, чтобы не смешиваться с выгрузкой в stdout
+    let subscriber = FmtSubscriber::builder()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")))
+        .with_writer(io::stderr)
+        .finish();
+    let _ = tracing::subscriber::set_global_default(subscriber);
+
+    dotenvy::dotenv().ok();
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", redact_api_key(&format!("{:#}", e)));
+            match e.downcast_ref::<ApiError>() {
+                Some(ApiError::Validation(_)) => ExitCode::from(EXIT_USAGE),
+                Some(ApiError::AlreadyRunning(_)) => ExitCode::from(EXIT_BUSY),
+                _ => ExitCode::from(EXIT_FAILURE),
+            }
+        }
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<ExitCode> {
+    let config = Config::from_env();
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&config.database_url)
+        .await?;
+
+    let code = match cli.command {
+        Command::Migrate { status } => migrate_command(&pool, status).await?,
+        command => {
+            migrate::ensure_applied(&pool).await?;
+            let state = AppState::new(pool.clone(), config, Vec::new(), CancellationToken::new())?;
+            data_command(&state, command).await?
+        }
+    };
+
+    pool.close().await;
+    Ok(code)
+}
+
+async fn migrate_command(pool: &PgPool, status: bool) -> anyhow::Result<ExitCode> {
+    if status {
+        let pending = migrate::pending(pool).await?;
+        for migration in &pending {
+            println!("pending {:04} {}", migration.version, migration.name);
+        }
+        if pending.is_empty() {
+            println!("schema is up to date");
+            return Ok(ExitCode::SUCCESS);
+        }
+        return Ok(ExitCode::from(EXIT_PARTIAL));
+    }
+
+    let applied = migrate::run(pool).await?;
+    println!("{} migration(s) applied", applied);
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn data_command(state: &AppState, command: Command) -> anyhow::Result<ExitCode> {
+    let pool = &state.pool;
+
+    match command {
+        Command::Migrate { .. } => unreachable!("handled before AppState is built"),
+        Command::Osdr { action: SyncAction::Sync } => {
+            let service = OsdrService::new(
+                pool.clone(),
+                state.nasa_client.clone(),
+                state.events.clone(),
+                state.config.osdr_mapping.clone(),
+            );
+            let report = exclusive(pool, &OsdrSyncJob, service.sync()).await?;
+            println!(
+                "osdr: {} inserted, {} updated, {} unchanged, {} marked missing, {} failed, {} skipped",
+                report.inserted, report.updated, report.unchanged, report.missing, report.failed, report.skipped
+            );
+        }
+        Command::Iss { action: FetchAction::Fetch } => {
+            exclusive(pool, &IssFetchJob, IssFetchJob.run(state)).await?;
+            println!("iss: position stored");
+        }
+        Command::Tle { action: FetchAction::Fetch } => {
+            let written = exclusive(pool, &TleFetchJob, TleFetchJob.run(state)).await?;
+            println!("tle: {} new set(s)", written.written);
+        }
+        Command::Space { action: SpaceAction::Refresh { sources } } => {
+            return space_refresh(state, sources).await;
+        }
+        Command::Space { action: SpaceAction::Backfill { source, from, to } } => {
+            let source = source.trim().to_lowercase();
+            let (from, to) = Validator::validate_backfill(&source, &from, &to)?;
+            let service = SpaceCacheService::new(
+                state.pool.clone(),
+                state.nasa_client.clone(),
+                state.spacex_client.clone(),
+                state.events.clone(),
+            );
+            let report = service.backfill(&source, from, to).await?;
+            let watermark = report.watermark.map(|d| d.to_string()).unwrap_or_else(|| "-".to_string());
+            println!(
+                "{}: {}..{} in {} window(s), {} record(s), watermark {}",
+                source, report.from, report.to, report.windows, report.records, watermark
+            );
+        }
+        Command::Export { table, source, from, to, format, output } => {
+            let from = Validator::validate_time(from.as_ref(), "from")?;
+            let to = Validator::validate_time(to.as_ref(), "to")?;
+            if let Some(source) = &source {
+                Validator::validate_source(source)?;
+            }
+            let format = match format {
+                FormatArg::Jsonl => ExportFormat::Jsonl,
+                FormatArg::Csv => ExportFormat::Csv,
+            };
+
+            let mut out: Box<dyn Write> = match &output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock())),
+            };
+            let service = MaintenanceService::new(pool.clone());
+            let rows = service
+                .export(table.into(), (from, to), source.as_deref(), format, &mut *out)
+                .await?;
+            eprintln!("exported {} row(s)", rows);
+        }
+        Command::Purge { table, older_than, source, dry_run } => {
+            let age = parse_duration(&older_than)
+                .filter(|d| *d > chrono::Duration::zero())
+                .ok_or_else(|| ApiError::Validation("--older-than must look like 30d, 12h or 90m".to_string()))?;
+            if let Some(source) = &source {
+                Validator::validate_source(source)?;
+            }
+
+            let table: DataTable = table.into();
+            let cutoff = chrono::Utc::now() - age;
+            let service = MaintenanceService::new(pool.clone());
+            let rows = service.purge(table, cutoff, source.as_deref(), dry_run).await?;
+            let verb = if dry_run { "would delete" } else { "deleted" };
+            println!("{}: {} {} row(s) older than {}", table.name(), verb, rows, cutoff);
+        }
+        Command::Retention { dry_run } => {
+            let service = RetentionService::new(pool.clone(), state.config.retention.clone());
+            let report = if dry_run {
+                service.run(true).await?
+            } else {
+                // Под lock задачи retention и с записью в job_runs, как у планировщика:
+                // параллельная свёртка посчитала бы одни и те же строки дважды
+                let mut report = None;
+                exclusive(pool, &RetentionJob, async {
+                    let done = service.run(false).await?;
+                    let deleted = done.deleted();
+                    report = Some(done);
+                    Ok(deleted)
+                })
+                .await?;
+                report.unwrap_or(RetentionReport { dry_run, entries: Vec::new() })
+            };
+            let verb = if dry_run { "would delete" } else { "deleted" };
+            for e in &report.entries {
+                let target = match &e.source {
+                    Some(source) => format!("{}/{}", e.target, source),
+                    None => e.target.clone(),
+                };
+                println!("{:<24} {:<32} {} {}, aggregated {}", target, e.policy, verb, e.deleted, e.aggregated);
+            }
+        }
+        Command::Stats { json } => {
+            let stats = MaintenanceService::new(pool.clone()).stats().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                println!("schema version: {}", stats.schema_version.unwrap_or(0));
+                println!("\n{:<24} {:>12} {:>12}", "table", "rows (est.)", "size");
+                for t in &stats.tables {
+                    println!("{:<24} {:>12} {:>12}", t.table, t.rows_estimate, human_bytes(t.total_bytes));
+                }
+                println!("\n{:<10} {:>8}  latest", "source", "rows");
+                for s in &stats.space_sources {
+                    let latest = s.latest_at.map(|t| t.to_rfc3339()).unwrap_or_default();
+                    println!("{:<10} {:>8}  {}", s.source, s.rows, latest);
+                }
+                println!("\n{:<10} {:<12} updated", "source", "covered to");
+                for w in &stats.watermarks {
+                    println!("{:<10} {:<12} {}", w.source, w.covered_to, w.updated_at.to_rfc3339());
+                }
+                println!("\n{:<10} {:<8} {:<8} finished", "job", "outcome", "trigger");
+                for j in &stats.jobs {
+                    if let Some(run) = &j.last_run {
+                        let finished = run.finished_at.map(|t| t.to_rfc3339()).unwrap_or_default();
+                        println!("{:<10} {:<8} {:<8} {}", j.name, run.outcome, run.trigger, finished);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// Каждая задача записывается в job_runs отдельно, flr и cme - одним запуском donki;
+// если часть упала - код 3, если все заняты другим запуском - код 4
+async fn space_refresh(state: &AppState, sources: Vec<String>) -> anyhow::Result<ExitCode> {
+    let sources = if sources.is_empty() {
+        ["apod", "neo", "flr", "cme", "spacex"].map(String::from).to_vec()
+    } else {
+        sources.iter().map(|s| s.trim().to_lowercase()).collect()
+    };
+    for source in &sources {
+        Validator::validate_source(source)?;
+    }
+
+    let jobs = space_jobs(sources.iter().map(String::as_str));
+    let mut failed = 0;
+    let mut busy = 0;
+    for job in &jobs {
+        let name = job.name();
+        match exclusive(&state.pool, *job, job.run(state)).await {
+            Ok(output) if output.written == 0 && output.unchanged > 0 => println!("{}: unchanged", name),
+            Ok(_) => println!("{}: refreshed", name),
+            Err(ApiError::AlreadyRunning(_)) => {
+                println!("{}: skipped, already running", name);
+                busy += 1;
+            }
+            Err(e) => {
+                println!("{}: failed: {}", name, redact_api_key(&e.to_string()));
+                failed += 1;
+            }
+        }
+    }
+
+    Ok(match (failed, busy) {
+        (0, 0) => ExitCode::SUCCESS,
+        (0, n) if n == jobs.len() => ExitCode::from(EXIT_BUSY),
+        (n, 0) if n == jobs.len() => ExitCode::from(EXIT_FAILURE),
+        _ => ExitCode::from(EXIT_PARTIAL),
+    })
+}
+
+// Разовый запуск под advisory lock задачи job, чтобы не пересечься с планировщиком
+async fn exclusive<F, T>(pool: &PgPool, job: &dyn Job, run: F) -> Result<T, ApiError>
+where
+    F: std::future::Future<Output = Result<T, ApiError>>,
+    T: Into<RunOutput> + Copy,
+{
+    run_exclusive(pool, job.lock_key(), job.name(), TRIGGER_CLI, run).await
+}
+
+fn human_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -1,10 +1,16 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
 use std::time::Duration;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, StatusCode, Url};
 use serde_json::Value;
 use tracing::warn;
 
-use crate::config::Config;
-use crate::domain::ApiError;
+use crate::config::{Config, OsdrCrawl};
+use crate::domain::{ApiError, NasaKeyQuota, OsdrListingEntry};
+use crate::scheduler::redact_api_key;
 
 pub struct HttpClient {
     client: Client,
@@ -22,28 +28,45 @@ impl HttpClient {
     }
     
     pub async fn get_with_retry(&self, url: &str) -> Result<Value, ApiError> {
+        let resp = self.send_with_retry(url).await?;
+        resp.json().await.map_err(|e| ApiError::Http(e.without_url()))
+    }
+    
+    pub async fn get_text_with_retry(&self, url: &str) -> Result<String, ApiError> {
+        let resp = self.send_with_retry(url).await?;
+        resp.text().await.map_err(|e| ApiError::Http(e.without_url()))
+    }
+    
+    // URL может содержать api_key: в логи и ошибки идёт только версия без ключа
+    async fn send_with_retry(&self, url: &str) -> Result<reqwest::Response, ApiError> {
+        let shown = redact_api_key(url);
         let mut last_error = None;
         
         for attempt in 0..=self.config.http_retries {
             match self.client.get(url).send().await {
                 Ok(resp) => {
                     if resp.status().is_success() {
-                        return resp.json().await.map_err(ApiError::Http);
+                        return Ok(resp);
+                    } else if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                        // Повтор сразу же упрётся в тот же лимит
+                        warn!("HTTP 429 for {}", shown);
+                        return Err(ApiError::RateLimited { retry_after: retry_after(resp.headers()) });
                     } else {
-                        warn!("HTTP {} for {} (attempt {})", resp.status(), url, attempt + 1);
+                        warn!("HTTP {} for {} (attempt {})", resp.status(), shown, attempt + 1);
                         if attempt < self.config.http_retries {
                             tokio::time::sleep(Duration::from_millis(100 * (attempt + 1) as u64)).await;
                             continue;
                         }
-                        return Err(ApiError::Internal(format!("HTTP {} from {}", resp.status(), url)));
+                        return Err(ApiError::Internal(format!("HTTP {} from {}", resp.status(), shown)));
                     }
                 }
                 Err(e) => {
-                    last_error = Some(e);
+                    let e = e.without_url();
                     if attempt < self.config.http_retries {
-                        warn!("Request failed (attempt {}): {:?}", attempt + 1, last_error);
+                        warn!("Request failed for {} (attempt {}): {:?}", shown, attempt + 1, e);
                         tokio::time::sleep(Duration::from_millis(100 * (attempt + 1) as u64)).await;
                     }
+                    last_error = Some(e);
                 }
             }
         }
@@ -70,82 +93,466 @@ impl IssClient {
     }
 }
 
+// Retry-After в секундах; форму с HTTP-датой NASA и SpaceX не используют
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn header_i64(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+// Первая пауза ключа после 429 без Retry-After, дальше удваивается до cooldown
+const NASA_THROTTLE_BACKOFF: Duration = Duration::from_secs(60);
+
+struct NasaKey {
+    key: String,
+    limit: Option<i64>,
+    remaining: Option<i64>,
+    requests: u64,
+    rate_limited: u64,
+    // 429 подряд, для роста паузы
+    throttled: u32,
+    resting_until: Option<DateTime<Utc>>,
+}
+
+// Пул ключей api.nasa.gov. Остаток квоты берётся из X-RateLimit-Remaining
+// последнего ответа; ключ с исчерпанной квотой или получивший 429 отдыхает,
+// запросы тем временем идут через остальные
+pub struct NasaKeyPool {
+    keys: Mutex<Vec<NasaKey>>,
+    cooldown: Duration,
+}
+
+impl NasaKeyPool {
+    // Без ключей запросы уходят без api_key, квота всё равно учитывается
+    pub fn new(keys: &[String], cooldown: Duration) -> Self {
+        let keys = if keys.is_empty() { vec![String::new()] } else { keys.to_vec() };
+        Self {
+            keys: Mutex::new(keys.into_iter().map(|key| NasaKey {
+                key,
+                limit: None,
+                remaining: None,
+                requests: 0,
+                rate_limited: 0,
+                throttled: 0,
+                resting_until: None,
+            }).collect()),
+            cooldown,
+        }
+    }
+    
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+    
+    // Ключ с наибольшим остатком; ещё не опрошенные идут первыми.
+    // Если отдыхают все - ошибка со временем до ближайшего освобождения
+    fn acquire(&self) -> Result<(usize, String), ApiError> {
+        let now = Utc::now();
+        let mut keys = self.lock();
+        for k in keys.iter_mut() {
+            if k.resting_until.is_some_and(|until| until <= now) {
+                // Квота за время отдыха восстановилась, прежний остаток устарел
+                k.resting_until = None;
+                k.remaining = None;
+            }
+        }
+        
+        let best = keys
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| k.resting_until.is_none())
+            .min_by_key(|(_, k)| (std::cmp::Reverse(k.remaining.unwrap_or(i64::MAX)), k.requests));
+        if let Some((i, k)) = best {
+            return Ok((i, k.key.clone()));
+        }
+        
+        let retry_after = keys
+            .iter()
+            .filter_map(|k| k.resting_until)
+            .min()
+            .and_then(|until| (until - now).to_std().ok());
+        Err(ApiError::RateLimited { retry_after })
+    }
+    
+    fn record(&self, index: usize, headers: &HeaderMap) {
+        let mut keys = self.lock();
+        let k = &mut keys[index];
+        k.requests += 1;
+        k.throttled = 0;
+        k.limit = header_i64(headers, "X-RateLimit-Limit").or(k.limit);
+        k.remaining = header_i64(headers, "X-RateLimit-Remaining");
+        if k.remaining.is_some_and(|r| r <= 0) {
+            warn!("NASA key {} has no quota left, resting for {:?}", mask_key(&k.key), self.cooldown);
+            k.resting_until = Some(Utc::now() + chrono::Duration::from_std(self.cooldown).unwrap_or_default());
+        }
+    }
+    
+    fn throttle(&self, index: usize, retry_after: Option<Duration>) {
+        let mut keys = self.lock();
+        let k = &mut keys[index];
+        k.requests += 1;
+        k.rate_limited += 1;
+        k.throttled += 1;
+        k.remaining = Some(0);
+        let pause = retry_after.unwrap_or_else(|| {
+            NASA_THROTTLE_BACKOFF
+                .saturating_mul(1 << (k.throttled - 1).min(16))
+                .min(self.cooldown)
+        });
+        warn!("NASA key {} got HTTP 429, resting for {:?}", mask_key(&k.key), pause);
+        k.resting_until = Some(Utc::now() + chrono::Duration::from_std(pause).unwrap_or_default());
+    }
+    
+    pub fn quotas(&self) -> Vec<NasaKeyQuota> {
+        self.lock().iter().map(|k| NasaKeyQuota {
+            key: mask_key(&k.key),
+            limit: k.limit,
+            remaining: k.remaining,
+            requests: k.requests,
+            rate_limited: k.rate_limited,
+            resting_until: k.resting_until,
+        }).collect()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<NasaKey>> {
+        // Состояние квот - подсказка, после паники в другом потоке им можно пользоваться
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn mask_key(key: &str) -> String {
+    match key {
+        "" => "none".to_string(),
+        "DEMO_KEY" => key.to_string(),
+        _ => format!("{}***", key.chars().take(4).collect::<String>()),
+    }
+}
+
 pub struct NasaClient {
     http: HttpClient,
     base_url: String,
-    api_key: String,
+    api_base: String,
+    pub keys: NasaKeyPool,
+    pub osdr: OsdrCrawl,
 }
 
 impl NasaClient {
     pub fn new(config: Config) -> Result<Self, ApiError> {
         Ok(Self {
             http: HttpClient::new(config.clone())?,
+            keys: NasaKeyPool::new(&config.nasa_keys, config.nasa_key_cooldown),
             base_url: config.nasa_url,
-            api_key: config.nasa_key,
+            api_base: config.nasa_api_base,
+            osdr: config.osdr_crawl,
         })
     }
     
-    pub async fn fetch_osdr(&self) -> Result<Value, ApiError> {
-        self.http.get_with_retry(&self.base_url).await
+    // Листинг OSDR (не на api.nasa.gov, ключ не нужен) по всем страницам: следующая
+    // берётся из next / links.next / pagination.next. Обрезанный листинг пометил бы
+    // остаток каталога пропавшим, поэтому зацикливание и больше max_pages страниц - ошибка
+    pub async fn fetch_osdr_listing(&self) -> Result<Vec<OsdrListingEntry>, ApiError> {
+        let mut entries = Vec::new();
+        let mut visited = HashSet::new();
+        let mut next = Some(self.base_url.clone());
+        
+        while let Some(url) = next {
+            if !visited.insert(url.clone()) {
+                return Err(ApiError::Internal(format!("OSDR listing loops back to {}", url)));
+            }
+            if visited.len() > self.osdr.max_pages {
+                return Err(ApiError::Internal(format!(
+                    "OSDR listing has more than {} pages (OSDR_MAX_PAGES)", self.osdr.max_pages
+                )));
+            }
+            let page = self.http.get_with_retry(&url).await?;
+            entries.extend(osdr_listing_entries(&page, &url));
+            next = osdr_next_page(&page, &url)?;
+        }
+        
+        Ok(entries)
+    }
+    
+    // Метаданные датасетов по (dataset_id, url), не больше osdr.concurrency запросов
+    // одновременно; ошибка одного датасета не прерывает остальные
+    pub async fn fetch_osdr_details(
+        &self,
+        targets: Vec<(String, String)>,
+    ) -> Vec<(String, Result<Value, ApiError>)> {
+        futures::stream::iter(targets)
+            .map(|(dataset_id, url)| async move {
+                let detail = self.http.get_with_retry(&url).await
+                    .map(|body| unwrap_dataset(body, &dataset_id));
+                (dataset_id, detail)
+            })
+            .buffer_unordered(self.osdr.concurrency)
+            .collect()
+            .await
+    }
+    
+    // Список файлов датасета; относительная ссылка - от адреса листинга. Ссылка берётся
+    // из сохранённого raw, поэтому ходить разрешено только туда же, откуда листинг
+    pub async fn fetch_osdr_files(&self, dataset_id: &str, url: &str) -> Result<Value, ApiError> {
+        let url = resolve_same_origin(&self.base_url, url)
+            .ok_or_else(|| ApiError::Internal(format!("invalid OSDR files URL: {}", url)))?;
+        let body = unwrap_dataset(self.http.get_with_retry(&url).await?, dataset_id);
+        Ok(match body.get("files") {
+            Some(files) => files.clone(),
+            None => body,
+        })
     }
     
     pub async fn fetch_apod(&self) -> Result<Value, ApiError> {
-        let api_key_param = if self.api_key.is_empty() {
-            String::new()
-        } else {
-            format!("&api_key={}", self.api_key)
-        };
-        let url = format!("https://api.nasa.gov/planetary/apod?thumbs=true{}", api_key_param);
-        self.http.get_with_retry(&url).await
+        let url = format!("{}/planetary/apod?thumbs=true", self.api_base);
+        self.get_with_key(&url).await
     }
     
     pub async fn fetch_neo_feed(&self, start_date: &str, end_date: &str) -> Result<Value, ApiError> {
-        let api_key_param = if self.api_key.is_empty() {
-            String::new()
-        } else {
-            format!("&api_key={}", self.api_key)
-        };
-        let url = format!("https://api.nasa.gov/neo/rest/v1/feed?start_date={}&end_date={}{}",
-            start_date, end_date, api_key_param);
-        self.http.get_with_retry(&url).await
+        let url = format!("{}/neo/rest/v1/feed?start_date={}&end_date={}",
+            self.api_base, start_date, end_date);
+        self.get_with_key(&url).await
     }
     
     pub async fn fetch_donki_flr(&self, start_date: &str, end_date: &str) -> Result<Value, ApiError> {
-        let api_key_param = if self.api_key.is_empty() {
-            String::new()
-        } else {
-            format!("&api_key={}", self.api_key)
-        };
-        let url = format!("https://api.nasa.gov/DONKI/FLR?startDate={}&endDate={}{}",
-            start_date, end_date, api_key_param);
-        self.http.get_with_retry(&url).await
+        let url = format!("{}/DONKI/FLR?startDate={}&endDate={}",
+            self.api_base, start_date, end_date);
+        self.get_with_key(&url).await
     }
     
     pub async fn fetch_donki_cme(&self, start_date: &str, end_date: &str) -> Result<Value, ApiError> {
-        let api_key_param = if self.api_key.is_empty() {
-            String::new()
-        } else {
-            format!("&api_key={}", self.api_key)
+        let url = format!("{}/DONKI/CME?startDate={}&endDate={}",
+            self.api_base, start_date, end_date);
+        self.get_with_key(&url).await
+    }
+    
+    // После 429 запрос повторяется со следующим ключом, каждый ключ - не больше раза
+    async fn get_with_key(&self, url: &str) -> Result<Value, ApiError> {
+        let mut last_error = None;
+        for _ in 0..self.keys.len() {
+            let (index, key) = self.keys.acquire()?;
+            let keyed = if key.is_empty() {
+                url.to_string()
+            } else {
+                format!("{}&api_key={}", url, key)
+            };
+            
+            match self.http.send_with_retry(&keyed).await {
+                Ok(resp) => {
+                    self.keys.record(index, resp.headers());
+                    return resp.json().await.map_err(|e| ApiError::Http(e.without_url()));
+                }
+                Err(ApiError::RateLimited { retry_after }) => {
+                    self.keys.throttle(index, retry_after);
+                    last_error = Some(ApiError::RateLimited { retry_after });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        
+        // Все ключи получили 429 в этом запросе - ждать до ближайшего освобождения
+        Err(match self.keys.acquire() {
+            Ok(_) => last_error.unwrap_or(ApiError::RateLimited { retry_after: None }),
+            Err(e) => e,
+        })
+    }
+}
+
+// Записи страницы листинга. Поддерживаются массив (сам ответ или items / results /
+// data / datasets) и объект {"OSD-1": {"REST_URL": ...}, ...}, как в biodata API v2.
+// Запись с REST_URL - заглушка, метаданные за ней запрашиваются отдельно.
+// Заглушка со ссылкой на другой хост (или схему, порт) отбрасывается
+fn osdr_listing_entries(page: &Value, page_url: &str) -> Vec<OsdrListingEntry> {
+    let container = ["items", "results", "data", "datasets"]
+        .iter()
+        .find_map(|k| page.get(*k).filter(|v| v.is_array() || v.is_object()))
+        .unwrap_or(page);
+    let detail_url = |item: &Value| {
+        item.get("REST_URL")
+            .and_then(|u| u.as_str())
+            .and_then(|u| resolve_same_origin(page_url, u))
+    };
+    let foreign = |entry: &OsdrListingEntry| {
+        let link = entry.item.get("REST_URL").and_then(|u| u.as_str());
+        let rejected = link.is_some_and(|u| resolve_same_origin(page_url, u).is_none());
+        if rejected {
+            warn!("OSDR listing entry {:?} links outside {}, skipped", entry.dataset_id, page_url);
+        }
+        rejected
+    };
+    
+    let entries = match container {
+        // Без идентификатора заглушку не с чем сопоставить, такая запись берётся как есть
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                let dataset_id = ["id", "dataset_id", "accession"]
+                    .iter()
+                    .find_map(|k| item.get(*k).and_then(|v| v.as_str()))
+                    .map(str::to_string);
+                OsdrListingEntry {
+                    detail_url: dataset_id.as_ref().and_then(|_| detail_url(item)),
+                    dataset_id,
+                    item: item.clone(),
+                }
+            })
+            .collect(),
+        Value::Object(map) if !map.is_empty() && map.values().all(|v| v.get("REST_URL").is_some()) => map
+            .iter()
+            .map(|(dataset_id, item)| OsdrListingEntry {
+                dataset_id: Some(dataset_id.clone()),
+                detail_url: detail_url(item),
+                item: item.clone(),
+            })
+            .collect(),
+        // Одиночный объект - один датасет, как раньше
+        other => vec![OsdrListingEntry { dataset_id: None, detail_url: None, item: other.clone() }],
+    };
+    entries.into_iter().filter(|e| !foreign(e)).collect()
+}
+
+// Ссылка на другой хост - ошибка: оборванный листинг пометил бы остаток каталога пропавшим
+fn osdr_next_page(page: &Value, page_url: &str) -> Result<Option<String>, ApiError> {
+    let next = ["/next", "/links/next", "/pagination/next"]
+        .iter()
+        .find_map(|p| page.pointer(p).and_then(|v| v.as_str()))
+        .filter(|next| !next.is_empty());
+    match next {
+        Some(next) => resolve_same_origin(page_url, next)
+            .map(Some)
+            .ok_or_else(|| ApiError::Internal(format!("OSDR listing next page '{}' leaves {}", next, page_url))),
+        None => Ok(None),
+    }
+}
+
+// Ссылка относительно base; схема, хост и порт должны совпасть с base
+fn resolve_same_origin(base: &str, link: &str) -> Option<String> {
+    let base = Url::parse(base).ok()?;
+    let url = base.join(link).ok()?;
+    (url.origin() == base.origin()).then(|| url.into())
+}
+
+// Ответы по датасету обёрнуты в {"OSD-1": {...}}
+fn unwrap_dataset(body: Value, dataset_id: &str) -> Value {
+    match body {
+        Value::Object(mut map) if map.len() == 1 && map.contains_key(dataset_id) => {
+            map.remove(dataset_id).unwrap_or_default()
+        }
+        other => other,
+    }
+}
+
+// TLE МКС: сначала по URL, при неудаче - из локального файла
+pub struct TleClient {
+    http: HttpClient,
+    pub url: String,
+    fallback_file: Option<String>,
+}
+
+impl TleClient {
+    pub fn new(config: Config) -> Result<Self, ApiError> {
+        Ok(Self {
+            http: HttpClient::new(config.clone())?,
+            url: config.tle_url,
+            fallback_file: config.tle_file,
+        })
+    }
+    
+    // Возвращает текст TLE и его источник
+    pub async fn fetch_tle(&self) -> Result<(String, String), ApiError> {
+        let err = match self.http.get_text_with_retry(&self.url).await {
+            Ok(text) => return Ok((text, self.url.clone())),
+            Err(e) => e,
         };
-        let url = format!("https://api.nasa.gov/DONKI/CME?startDate={}&endDate={}{}",
-            start_date, end_date, api_key_param);
-        self.http.get_with_retry(&url).await
+        
+        let Some(path) = &self.fallback_file else {
+            return Err(err);
+        };
+        warn!("TLE fetch from {} failed ({}), using {}", self.url, err, path);
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ApiError::Internal(format!("cannot read TLE file {}: {}", path, e)))?;
+        Ok((text, format!("file:{}", path)))
     }
 }
 
 pub struct SpaceXClient {
     http: HttpClient,
+    base_url: String,
 }
 
 impl SpaceXClient {
     pub fn new(config: Config) -> Result<Self, ApiError> {
         Ok(Self {
-            http: HttpClient::new(config)?,
+            http: HttpClient::new(config.clone())?,
+            base_url: config.spacex_api_base,
         })
     }
     
     pub async fn fetch_next_launch(&self) -> Result<Value, ApiError> {
-        self.http.get_with_retry("https://api.spacexdata.com/v4/launches/next").await
+        let url = format!("{}/v4/launches/next", self.base_url);
+        self.http.get_with_retry(&url).await
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_url_stays_on_listing_origin() {
+        let base = "https://visualization.osdr.nasa.gov/biodata/api/v2/datasets/?format=json";
+        let allowed = [
+            ("/biodata/api/v2/dataset/OSD-48/files/", "https://visualization.osdr.nasa.gov/biodata/api/v2/dataset/OSD-48/files/"),
+            ("../dataset/OSD-48/files/", "https://visualization.osdr.nasa.gov/biodata/api/v2/dataset/OSD-48/files/"),
+            ("https://visualization.osdr.nasa.gov:443/x", "https://visualization.osdr.nasa.gov/x"),
+        ];
+        for (link, expected) in allowed {
+            assert_eq!(resolve_same_origin(base, link).as_deref(), Some(expected), "{}", link);
+        }
+        let rejected = [
+            "http://visualization.osdr.nasa.gov/biodata/",
+            "https://visualization.osdr.nasa.gov:8443/biodata/",
+            "https://evil.example/biodata/",
+            "//169.254.169.254/latest/meta-data/",
+            "http://localhost:5432/",
+            "file:///etc/passwd",
+            "data:text/plain,hi",
+        ];
+        for link in rejected {
+            assert_eq!(resolve_same_origin(base, link), None, "{}", link);
+
+            // Заглушки листинга (объект и массив) и ссылка на следующую страницу
+            let page = serde_json::json!({
+                "OSD-1": { "REST_URL": link },
+                "OSD-2": { "REST_URL": "/biodata/api/v2/dataset/OSD-2/" },
+            });
+            let ids: Vec<_> = osdr_listing_entries(&page, base).into_iter().filter_map(|e| e.dataset_id).collect();
+            assert_eq!(ids, ["OSD-2"], "{}", link);
+            let page = serde_json::json!({ "items": [{ "id": "OSD-1", "REST_URL": link }, { "id": "OSD-3" }] });
+            let ids: Vec<_> = osdr_listing_entries(&page, base).into_iter().filter_map(|e| e.dataset_id).collect();
+            assert_eq!(ids, ["OSD-3"], "{}", link);
+            for pointer in ["next", "links", "pagination"] {
+                let page = match pointer {
+                    "next" => serde_json::json!({ "next": link }),
+                    key => serde_json::json!({ key: { "next": link } }),
+                };
+                assert!(osdr_next_page(&page, base).is_err(), "{} {}", pointer, link);
+            }
+        }
+
+        let page = serde_json::json!({ "results": {}, "next": "?format=json&page=2" });
+        assert_eq!(
+            osdr_next_page(&page, base).unwrap().as_deref(),
+            Some("https://visualization.osdr.nasa.gov/biodata/api/v2/datasets/?format=json&page=2")
+        );
+        assert_eq!(osdr_next_page(&serde_json::json!({ "next": null }), base).unwrap(), None);
+    }
+}
@@ -1,16 +1,72 @@
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::domain::ApiError;
+use crate::mapping::OsdrMapping;
+use crate::validation::parse_duration;
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub database_url: String,
+    pub db_max_connections: u32,
     pub redis_url: Option<String>,
     pub nasa_url: String,
-    pub nasa_key: String,
+    pub osdr_crawl: OsdrCrawl,
+    pub osdr_mapping: Arc<OsdrMapping>,
+    pub nasa_api_base: String,
+    pub spacex_api_base: String,
+    pub nasa_keys: Vec<String>,
+    pub nasa_key_cooldown: Duration,
     pub where_iss_url: String,
+    pub tle_url: String,
+    pub tle_file: Option<String>,
     pub fetch_intervals: FetchIntervals,
+    pub fetch_crons: FetchCrons,
+    pub job_jitter: Duration,
+    pub job_concurrency: usize,
     pub http_timeout: Duration,
     pub http_retries: u32,
     pub rate_limit_per_minute: u32,
+    pub shutdown_timeout: Duration,
+    pub event_bus: EventBusKind,
+    pub migrate_on_start: bool,
+    pub retention: RetentionPolicy,
+}
+
+// Обход каталога OSDR. detail_refresh - как часто перезапрашивать метаданные
+// уже известного датасета; None - на каждой синхронизации
+#[derive(Clone, Debug)]
+pub struct OsdrCrawl {
+    pub concurrency: usize,
+    pub max_pages: usize,
+    pub detail_refresh: Option<chrono::Duration>,
+}
+
+// Сроки хранения; None - хранить без ограничений
+#[derive(Clone, Debug)]
+pub struct RetentionPolicy {
+    pub iss_raw: Option<chrono::Duration>,
+    pub iss_hourly: Option<chrono::Duration>,
+    pub space_cache_keep: Option<i64>,
+    pub space_cache_keep_by_source: HashMap<String, Option<i64>>,
+}
+
+impl RetentionPolicy {
+    pub fn space_cache_keep(&self, source: &str) -> Option<i64> {
+        self.space_cache_keep_by_source
+            .get(source)
+            .copied()
+            .unwrap_or(self.space_cache_keep)
+    }
+}
+
+// local - события видны только внутри процесса,
+// postgres - через LISTEN/NOTIFY, для нескольких реплик
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventBusKind {
+    Local,
+    Postgres,
 }
 
 #[derive(Clone, Debug)]
@@ -21,6 +77,21 @@ pub struct FetchIntervals {
     pub neo: u64,
     pub donki: u64,
     pub spacex: u64,
+    pub tle: u64,
+    pub retention: u64,
+}
+
+// Необязательные cron-выражения, переопределяющие интервалы
+#[derive(Clone, Debug, Default)]
+pub struct FetchCrons {
+    pub osdr: Option<String>,
+    pub iss: Option<String>,
+    pub apod: Option<String>,
+    pub neo: Option<String>,
+    pub donki: Option<String>,
+    pub spacex: Option<String>,
+    pub tle: Option<String>,
+    pub retention: Option<String>,
 }
 
 impl Config {
@@ -33,11 +104,46 @@ impl Config {
         let nasa_url = std::env::var("NASA_API_URL")
             .unwrap_or_else(|_| "https://visualization.osdr.nasa.gov/biodata/api/v2/datasets/?format=json".to_string());
         
-        let nasa_key = std::env::var("NASA_API_KEY").unwrap_or_default();
+        let osdr_crawl = OsdrCrawl {
+            concurrency: env_u64("OSDR_CONCURRENCY", 4).max(1) as usize,
+            max_pages: env_u64("OSDR_MAX_PAGES", 200).max(1) as usize,
+            detail_refresh: env_retention("OSDR_DETAIL_REFRESH", "24h"),
+        };
+        
+        // Схема ответа OSDR меняется - пути к полям в файле, а не в коде
+        let osdr_mapping = Arc::new(match env_opt("OSDR_MAPPING_FILE") {
+            Some(path) => OsdrMapping::load(&path)
+                .unwrap_or_else(|e| panic!("OSDR_MAPPING_FILE '{}' is invalid: {}", path, e)),
+            None => OsdrMapping::builtin(),
+        });
+        
+        // Базовые адреса api.nasa.gov (APOD, NEO, DONKI) и SpaceX; для тестов без сети
+        // их можно направить на mock_upstream
+        let nasa_api_base = env_base_url("NASA_API_BASE", "https://api.nasa.gov");
+        let spacex_api_base = env_base_url("SPACEX_API_BASE", "https://api.spacexdata.com");
+        
+        // Пул ключей через запятую; NASA_API_KEY - для одного ключа
+        let nasa_keys: Vec<String> = env_opt("NASA_API_KEYS")
+            .or_else(|| env_opt("NASA_API_KEY"))
+            .map(|keys| {
+                keys.split(',')
+                    .map(|k| k.trim().to_string())
+                    .filter(|k| !k.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        // Сколько ключ с исчерпанной квотой не используется; лимит NASA считается по часу
+        let nasa_key_cooldown = Duration::from_secs(env_u64("NASA_KEY_COOLDOWN_SECONDS", 3600));
         
         let where_iss_url = std::env::var("WHERE_ISS_URL")
             .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/25544".to_string());
         
+        let tle_url = std::env::var("TLE_URL")
+            .unwrap_or_else(|_| "https://celestrak.org/NORAD/elements/gp.php?CATNR=25544&FORMAT=TLE".to_string());
+        
+        let tle_file = env_opt("TLE_FILE");
+        
         let fetch_intervals = FetchIntervals {
             osdr: env_u64("FETCH_EVERY_SECONDS", 600),
             iss: env_u64("ISS_EVERY_SECONDS", 120),
@@ -45,30 +151,106 @@ impl Config {
             neo: env_u64("NEO_EVERY_SECONDS", 7200),
             donki: env_u64("DONKI_EVERY_SECONDS", 3600),
             spacex: env_u64("SPACEX_EVERY_SECONDS", 3600),
+            tle: env_u64("TLE_EVERY_SECONDS", 21600),
+            retention: env_u64("RETENTION_EVERY_SECONDS", 3600),
+        };
+        
+        let fetch_crons = FetchCrons {
+            osdr: env_opt("OSDR_CRON"),
+            iss: env_opt("ISS_CRON"),
+            apod: env_opt("APOD_CRON"),
+            neo: env_opt("NEO_CRON"),
+            donki: env_opt("DONKI_CRON"),
+            spacex: env_opt("SPACEX_CRON"),
+            tle: env_opt("TLE_CRON"),
+            retention: env_opt("RETENTION_CRON"),
         };
         
+        let job_jitter = Duration::from_secs(env_u64("JOB_JITTER_SECONDS", 5));
+        
+        let job_concurrency = env_u64("JOB_CONCURRENCY", 2) as usize;
+        
+        let db_max_connections = env_u64("DB_MAX_CONNECTIONS", 10) as u32;
+        
         let http_timeout = Duration::from_secs(
-            env_u64("HTTP_TIMEOUT_SECONDS", 30) as u64
+            env_u64("HTTP_TIMEOUT_SECONDS", 30)
         );
         
         let http_retries = env_u64("HTTP_RETRIES", 3) as u32;
         
         let rate_limit_per_minute = env_u64("RATE_LIMIT_PER_MINUTE", 60) as u32;
         
+        let shutdown_timeout = Duration::from_secs(env_u64("SHUTDOWN_TIMEOUT_SECONDS", 25));
+        
+        let event_bus = match env_opt("EVENT_BUS").as_deref() {
+            None | Some("local") => EventBusKind::Local,
+            Some("postgres") => EventBusKind::Postgres,
+            Some(other) => panic!("EVENT_BUS must be 'local' or 'postgres', got '{}'", other),
+        };
+        
+        // false - схему обновляет отдельный запуск `rust_iss --migrate-only`
+        let migrate_on_start = env_opt("MIGRATE_ON_START").as_deref() != Some("false");
+        
+        // 0 в любой из настроек отключает соответствующую очистку
+        let retention = RetentionPolicy {
+            iss_raw: env_retention("ISS_RAW_RETENTION", "7d"),
+            iss_hourly: env_retention("ISS_HOURLY_RETENTION", "365d"),
+            space_cache_keep: env_keep("SPACE_CACHE_KEEP", 500),
+            space_cache_keep_by_source: ["apod", "neo", "flr", "cme", "spacex"]
+                .iter()
+                .filter_map(|source| {
+                    let key = format!("SPACE_CACHE_KEEP_{}", source.to_uppercase());
+                    env_opt(&key).map(|_| (source.to_string(), env_keep(&key, 0)))
+                })
+                .collect(),
+        };
+        
         Self {
             database_url,
+            db_max_connections,
             redis_url,
             nasa_url,
-            nasa_key,
+            osdr_crawl,
+            osdr_mapping,
+            nasa_api_base,
+            spacex_api_base,
+            nasa_keys,
+            nasa_key_cooldown,
             where_iss_url,
+            tle_url,
+            tle_file,
             fetch_intervals,
+            fetch_crons,
+            job_jitter,
+            job_concurrency,
             http_timeout,
             http_retries,
             rate_limit_per_minute,
+            shutdown_timeout,
+            event_bus,
+            migrate_on_start,
+            retention,
+        }
+    }
+
+    // Каждая идущая задача держит соединение с advisory lock и берёт ещё одно на запросы;
+    // остаток - HTTP-обработчикам. Параллелизм считается как в Scheduler::new
+    pub fn check_db_pool(&self) -> Result<(), ApiError> {
+        let concurrency = self.job_concurrency.max(1) as u32;
+        let required = 2 * concurrency + DB_POOL_HEADROOM;
+        if self.db_max_connections < required {
+            return Err(ApiError::Validation(format!(
+                "DB_MAX_CONNECTIONS={} is too small for JOB_CONCURRENCY={}: need at least {}",
+                self.db_max_connections, concurrency, required
+            )));
         }
+        Ok(())
     }
 }
 
+// Соединения сверх нужных задачам: HTTP-обработчики, миграции, /health
+const DB_POOL_HEADROOM: u32 = 2;
+
 fn env_u64(key: &str, default: u64) -> u64 {
     std::env::var(key)
         .ok()
@@ -76,3 +258,37 @@ fn env_u64(key: &str, default: u64) -> u64 {
         .unwrap_or(default)
 }
 
+fn env_opt(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn env_base_url(key: &str, default: &str) -> String {
+    env_opt(key)
+        .unwrap_or_else(|| default.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// Срок вида 7d / 12h; 0 - без ограничения
+fn env_retention(key: &str, default: &str) -> Option<chrono::Duration> {
+    let value = env_opt(key).unwrap_or_else(|| default.to_string());
+    let duration = parse_duration(&value)
+        .filter(|d| *d >= chrono::Duration::zero())
+        .unwrap_or_else(|| panic!("{} must look like 7d, 12h or 0, got '{}'", key, value));
+    (duration > chrono::Duration::zero()).then_some(duration)
+}
+
+fn env_keep(key: &str, default: i64) -> Option<i64> {
+    let keep = match env_opt(key) {
+        Some(value) => value
+            .parse::<i64>()
+            .ok()
+            .filter(|n| *n >= 0)
+            .unwrap_or_else(|| panic!("{} must be a non-negative number, got '{}'", key, value)),
+        None => default,
+    };
+    (keep > 0).then_some(keep)
+}
@@ -0,0 +1,116 @@
+use serde_json::{Map, Value};
+
+// Разница двух JSON-документов списком операций в духе RFC 6902:
+// {"op": "add" | "remove" | "replace", "path": JSON Pointer, "old", "new"}.
+// Объекты сравниваются по ключам рекурсивно, массивы и скаляры - целиком
+pub fn json_diff(old: &Value, new: &Value) -> Value {
+    let mut ops = Vec::new();
+    diff_into(&mut ops, String::new(), old, new);
+    Value::Array(ops)
+}
+
+fn diff_into(ops: &mut Vec<Value>, path: String, old: &Value, new: &Value) {
+    if old == new {
+        return;
+    }
+    
+    let (Value::Object(old_map), Value::Object(new_map)) = (old, new) else {
+        ops.push(op("replace", path, Some(old), Some(new)));
+        return;
+    };
+    
+    for (key, old_value) in old_map {
+        let child = format!("{}/{}", path, escape(key));
+        match new_map.get(key) {
+            Some(new_value) => diff_into(ops, child, old_value, new_value),
+            None => ops.push(op("remove", child, Some(old_value), None)),
+        }
+    }
+    for (key, new_value) in new_map {
+        if !old_map.contains_key(key) {
+            ops.push(op("add", format!("{}/{}", path, escape(key)), None, Some(new_value)));
+        }
+    }
+}
+
+fn op(kind: &str, path: String, old: Option<&Value>, new: Option<&Value>) -> Value {
+    let mut map = Map::new();
+    map.insert("op".to_string(), kind.into());
+    map.insert("path".to_string(), path.into());
+    if let Some(old) = old {
+        map.insert("old".to_string(), old.clone());
+    }
+    if let Some(new) = new {
+        map.insert("new".to_string(), new.clone());
+    }
+    Value::Object(map)
+}
+
+// Экранирование сегмента JSON Pointer (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_cases() {
+        let cases = [
+            ("equal", json!({"a": 1}), json!({"a": 1}), json!([])),
+            ("add", json!({"a": 1}), json!({"a": 1, "b": 2}), json!([{"op": "add", "path": "/b", "new": 2}])),
+            ("remove", json!({"a": 1, "b": 2}), json!({"a": 1}), json!([{"op": "remove", "path": "/b", "old": 2}])),
+            ("replace", json!({"a": 1}), json!({"a": "1"}), json!([{"op": "replace", "path": "/a", "old": 1, "new": "1"}])),
+            ("null is a value", json!({"a": null}), json!({}), json!([{"op": "remove", "path": "/a", "old": null}])),
+            (
+                "nested",
+                json!({"study": {"title": "Old", "meta": {"x": 1}}}),
+                json!({"study": {"title": "New", "meta": {"x": 1, "y": 2}}}),
+                json!([
+                    {"op": "add", "path": "/study/meta/y", "new": 2},
+                    {"op": "replace", "path": "/study/title", "old": "Old", "new": "New"},
+                ]),
+            ),
+            (
+                "arrays as a whole",
+                json!({"files": [1, 2]}),
+                json!({"files": [1, 3]}),
+                json!([{"op": "replace", "path": "/files", "old": [1, 2], "new": [1, 3]}]),
+            ),
+            (
+                "object becomes scalar",
+                json!({"a": {"b": 1}}),
+                json!({"a": 5}),
+                json!([{"op": "replace", "path": "/a", "old": {"b": 1}, "new": 5}]),
+            ),
+            ("root", json!([1]), json!({"a": 1}), json!([{"op": "replace", "path": "", "old": [1], "new": {"a": 1}}])),
+            (
+                "escaped keys",
+                json!({"a/b": 1, "m~n": {"~/": 1}}),
+                json!({"a/b": 2, "m~n": {"~/": 2}}),
+                json!([
+                    {"op": "replace", "path": "/a~1b", "old": 1, "new": 2},
+                    {"op": "replace", "path": "/m~0n/~0~1", "old": 1, "new": 2},
+                ]),
+            ),
+        ];
+        for (name, old, new, expected) in cases {
+            assert_eq!(json_diff(&old, &new), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn paths_resolve_in_both_documents() {
+        let old = json!({"a/b": {"~": 1, "gone": true}, "x": [1]});
+        let new = json!({"a/b": {"~": 2, "new/~": "v"}, "x": [2]});
+        let ops = json_diff(&old, &new);
+        assert_eq!(ops.as_array().unwrap().len(), 4);
+        for op in ops.as_array().unwrap() {
+            let path = op["path"].as_str().unwrap();
+            assert_eq!(old.pointer(path), op.get("old"), "{}", path);
+            assert_eq!(new.pointer(path), op.get("new"), "{}", path);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -10,6 +10,18 @@ pub struct IssFetchLog {
     pub fetched_at: DateTime<Utc>,
     pub source_url: String,
     pub payload: Value,
+    pub position: Option<IssPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub velocity: Option<f64>,
+    pub visibility: Option<String>,
+    pub footprint: Option<f64>,
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,7 +32,102 @@ pub struct OsdrItem {
     pub status: Option<String>,
     pub updated_at: Option<DateTime<Utc>>,
     pub inserted_at: DateTime<Utc>,
+    pub missing_since: Option<DateTime<Utc>>,
+    pub details_fetched_at: Option<DateTime<Utc>>,
+    pub raw: Value,
+}
+
+// Запись листинга OSDR: готовый датасет или заглушка со ссылкой на его метаданные
+#[derive(Debug, Clone)]
+pub struct OsdrListingEntry {
+    pub dataset_id: Option<String>,
+    pub detail_url: Option<String>,
+    pub item: Value,
+}
+
+// Датасет из выдачи OSDR, подготовленный к записи;
+// details_fetched - метаданные запрошены в эту синхронизацию
+#[derive(Debug, Clone)]
+pub struct OsdrRecord {
+    pub dataset_id: Option<String>,
+    pub title: Option<String>,
+    pub status: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
     pub raw: Value,
+    pub details_fetched: bool,
+}
+
+// Итог синхронизации: unchanged - raw совпал с сохранённым,
+// missing - сколько датасетов впервые не нашлось в выдаче,
+// failed - новые датасеты, метаданные которых не удалось получить,
+// skipped - записи без dataset_id: сопоставить их с сохранёнными не по чему
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct OsdrSyncReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub missing: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl OsdrSyncReport {
+    pub fn written(&self) -> usize {
+        self.inserted + self.updated
+    }
+}
+
+// Ревизия raw датасета; diff - операции относительно предыдущей версии (у первой - None),
+// raw отдаётся только по запросу
+#[derive(Debug, Clone, Serialize)]
+pub struct OsdrItemVersion {
+    pub version: i32,
+    pub recorded_at: DateTime<Utc>,
+    pub title: Option<String>,
+    pub status: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub diff: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsdrSort {
+    Relevance,
+    Updated,
+    Inserted,
+}
+
+// Позиция после последней строки страницы: ранг или время сортировки и id
+#[derive(Debug, Clone, Copy)]
+pub enum OsdrCursor {
+    Rank(f32, i64),
+    Time(DateTime<Utc>, i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct OsdrSearchQuery {
+    pub q: Option<String>,
+    pub status: Option<String>,
+    pub updated_since: Option<DateTime<Utc>>,
+    pub sort: OsdrSort,
+    pub after: Option<OsdrCursor>,
+    pub limit: i64,
+}
+
+// rank и snippet есть только при поиске по q; совпадения в snippet обёрнуты в <mark>
+#[derive(Debug, Clone, Serialize)]
+pub struct OsdrSearchHit {
+    #[serde(flatten)]
+    pub item: OsdrItem,
+    pub rank: Option<f32>,
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OsdrSearchPage {
+    pub items: Vec<OsdrSearchHit>,
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,9 +135,161 @@ pub struct SpaceCache {
     pub id: i64,
     pub source: String,
     pub fetched_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub payload_hash: String,
     pub payload: Value,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceCachePage {
+    pub source: String,
+    pub items: Vec<SpaceCache>,
+    // Передаётся как ?cursor= для следующей страницы; None - страниц больше нет
+    pub next_cursor: Option<String>,
+}
+
+// Объект из ленты NeoWs вместе с его сближениями
+#[derive(Debug, Clone, Serialize)]
+pub struct NeoObject {
+    pub id: String,
+    pub name: String,
+    pub nasa_jpl_url: Option<String>,
+    pub absolute_magnitude: Option<f64>,
+    pub diameter_min_km: Option<f64>,
+    pub diameter_max_km: Option<f64>,
+    pub hazardous: bool,
+    pub sentry: bool,
+    pub approaches: Vec<NeoCloseApproach>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NeoCloseApproach {
+    pub approach_at: DateTime<Utc>,
+    pub miss_distance_km: Option<f64>,
+    pub relative_velocity_kms: Option<f64>,
+    pub orbiting_body: Option<String>,
+}
+
+// Строка ответа /neo: сближение вместе с данными объекта
+#[derive(Debug, Clone, Serialize)]
+pub struct NeoApproach {
+    pub neo_id: String,
+    pub name: String,
+    pub nasa_jpl_url: Option<String>,
+    pub hazardous: bool,
+    pub diameter_min_km: Option<f64>,
+    pub diameter_max_km: Option<f64>,
+    pub approach_at: DateTime<Utc>,
+    pub miss_distance_km: Option<f64>,
+    pub relative_velocity_kms: Option<f64>,
+    pub orbiting_body: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeoSort {
+    Approach,
+    Miss,
+    Velocity,
+    Diameter,
+}
+
+#[derive(Debug, Clone)]
+pub struct NeoQuery {
+    pub hazardous: Option<bool>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub max_miss_km: Option<f64>,
+    pub sort: NeoSort,
+    pub limit: i64,
+}
+
+// Вспышка DONKI FLR; linked_ids - связанные события DONKI из linkedEvents
+#[derive(Debug, Clone, Serialize)]
+pub struct Flare {
+    pub id: String,
+    pub class_type: Option<String>,
+    pub class_flux: Option<f64>,
+    pub begin_at: Option<DateTime<Utc>>,
+    pub peak_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub source_location: Option<String>,
+    pub active_region: Option<i32>,
+    pub link: Option<String>,
+    pub linked_ids: Vec<String>,
+}
+
+impl Flare {
+    // Рентгеновский класс вспышки -> пиковый поток, Вт/м2: M1.2 -> 1.2e-5, X -> 1e-4
+    pub fn flux_for_class(class: &str) -> Option<f64> {
+        let class = class.trim();
+        let mut chars = class.chars();
+        let base = match chars.next()?.to_ascii_uppercase() {
+            'A' => 1e-8,
+            'B' => 1e-7,
+            'C' => 1e-6,
+            'M' => 1e-5,
+            'X' => 1e-4,
+            _ => return None,
+        };
+        let magnitude = chars.as_str();
+        if magnitude.is_empty() {
+            return Some(base);
+        }
+        magnitude.parse::<f64>().ok().filter(|m| *m > 0.0).map(|m| base * m)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Cme {
+    pub id: String,
+    pub start_at: Option<DateTime<Utc>>,
+    pub speed_kms: Option<f64>,
+    pub half_angle_deg: Option<f64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub cme_type: Option<String>,
+    pub source_location: Option<String>,
+    pub active_region: Option<i32>,
+    pub note: Option<String>,
+    pub link: Option<String>,
+    pub linked_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlareQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub min_flux: Option<f64>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CmeQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub min_speed: Option<f64>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssTrackPoint {
+    pub id: i64,
+    pub at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub velocity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssHistory {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total: usize,
+    pub returned: usize,
+    pub points: Vec<IssTrackPoint>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct IssTrend {
     pub movement: bool,
@@ -45,10 +304,219 @@ pub struct IssTrend {
     pub to_lon: Option<f64>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: i64,
+    pub job_name: String,
+    pub trigger: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub outcome: String,
+    pub error: Option<String>,
+    pub rows_written: Option<i64>,
+    pub rows_unchanged: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub schedule: Option<String>,
+    pub last_run: Option<JobRun>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssWindowTrend {
+    pub window_sec: i64,
+    pub from_time: Option<DateTime<Utc>>,
+    pub to_time: Option<DateTime<Utc>>,
+    pub samples: usize,
+    pub distance_km: f64,
+    pub mean_velocity_kmh: Option<f64>,
+    pub max_velocity_kmh: Option<f64>,
+    pub min_altitude_km: Option<f64>,
+    pub max_altitude_km: Option<f64>,
+    pub heading_deg: Option<f64>,
+    pub expected_interval_sec: u64,
+    pub gaps: Vec<IssSamplingGap>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssSamplingGap {
+    pub from_time: DateTime<Utc>,
+    pub to_time: DateTime<Utc>,
+    pub dt_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TleSet {
+    pub id: i64,
+    pub norad_id: i32,
+    pub name: Option<String>,
+    pub line1: String,
+    pub line2: String,
+    pub epoch: DateTime<Utc>,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssPredictedPoint {
+    pub at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+    pub velocity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PredictionAccuracy {
+    pub samples: usize,
+    pub mean_error_km: f64,
+    pub max_error_km: f64,
+    pub rms_error_km: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssPrediction {
+    pub tle: TleSet,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub step_sec: i64,
+    pub points: Vec<IssPredictedPoint>,
+    pub accuracy: Option<PredictionAccuracy>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssPassEvent {
+    pub at: DateTime<Utc>,
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub range_km: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssPass {
+    pub rise: IssPassEvent,
+    pub culmination: IssPassEvent,
+    pub set: IssPassEvent,
+    pub max_elevation: f64,
+    pub duration_sec: i64,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssPasses {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude_m: f64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub min_elevation: f64,
+    pub tle_epoch: DateTime<Utc>,
+    pub passes: Vec<IssPass>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableStats {
+    pub table: String,
+    pub rows_estimate: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStats {
+    pub source: String,
+    pub rows: i64,
+    pub latest_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DbStats {
+    pub schema_version: Option<i64>,
+    pub tables: Vec<TableStats>,
+    pub space_sources: Vec<SourceStats>,
+    pub watermarks: Vec<FetchWatermark>,
+    pub jobs: Vec<JobStatus>,
+}
+
+// Дата, по которую включительно загружен источник с датированными окнами
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchWatermark {
+    pub source: String,
+    pub covered_to: NaiveDate,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillReport {
+    pub source: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub windows: usize,
+    pub records: usize,
+    pub watermark: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssHourly {
+    pub hour: DateTime<Utc>,
+    pub samples: i32,
+    // Строки с известной высотой и скоростью: по ним считаются средние
+    pub altitude_samples: i32,
+    pub velocity_samples: i32,
+    pub first_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+    pub min_latitude: Option<f64>,
+    pub max_latitude: Option<f64>,
+    pub avg_altitude: Option<f64>,
+    pub min_altitude: Option<f64>,
+    pub max_altitude: Option<f64>,
+    pub avg_velocity: Option<f64>,
+}
+
+// Строка отчёта политики хранения; id и ran_at заполнены у записанных в retention_log
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionEntry {
+    pub id: Option<i64>,
+    pub ran_at: Option<DateTime<Utc>>,
+    pub target: String,
+    pub source: Option<String>,
+    pub policy: String,
+    pub aggregated: i64,
+    pub deleted: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub entries: Vec<RetentionEntry>,
+}
+
+impl RetentionReport {
+    pub fn deleted(&self) -> usize {
+        self.entries.iter().map(|e| e.deleted as usize).sum()
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Health {
     pub status: &'static str,
     pub now: DateTime<Utc>,
+    pub nasa_keys: Vec<NasaKeyQuota>,
+}
+
+// Квота ключа NASA по заголовкам X-RateLimit-* последнего ответа; ключ замаскирован
+#[derive(Debug, Clone, Serialize)]
+pub struct NasaKeyQuota {
+    pub key: String,
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+    pub requests: u64,
+    pub rate_limited: u64,
+    pub resting_until: Option<DateTime<Utc>>,
 }
 
 // Error types
@@ -68,18 +536,30 @@ pub enum ApiError {
     
     #[error("Internal error: {0}")]
     Internal(String),
+    
+    #[error("Upstream rate limit exceeded")]
+    RateLimited { retry_after: Option<std::time::Duration> },
+    
+    // Advisory lock задачи держит другой запуск (планировщик, другая реплика или CLI)
+    #[error("Job '{0}' is already running")]
+    AlreadyRunning(String),
 }
 
 impl axum::response::IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
+        let retry_after = match &self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        };
         let (status, error_message) = match self {
             ApiError::Database(e) => (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Database error: {}", e),
             ),
+            // Ошибка reqwest несёт URL запроса, а в нём может быть api_key
             ApiError::Http(e) => (
                 axum::http::StatusCode::BAD_GATEWAY,
-                format!("HTTP error: {}", e),
+                format!("HTTP error: {}", e.without_url()),
             ),
             ApiError::Validation(msg) => (
                 axum::http::StatusCode::BAD_REQUEST,
@@ -93,13 +573,79 @@ impl axum::response::IntoResponse for ApiError {
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 msg,
             ),
+            ApiError::RateLimited { .. } => (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "Upstream rate limit exceeded".to_string(),
+            ),
+            ApiError::AlreadyRunning(job) => (
+                axum::http::StatusCode::CONFLICT,
+                format!("Job '{}' is already running", job),
+            ),
         };
         
         let body = serde_json::json!({
             "error": error_message,
         });
         
-        (status, axum::Json(body)).into_response()
+        let mut response = (status, axum::Json(body)).into_response();
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                axum::http::HeaderValue::from(retry_after.as_secs().max(1)),
+            );
+        }
+        response
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn http_error_response_hides_request_url() {
+        // Порт 9 (discard) закрыт - соединение отклоняется сразу
+        let err = reqwest::get("http://127.0.0.1:9/planetary/apod?api_key=SECRET").await.unwrap_err();
+        assert!(err.to_string().contains("SECRET"));
+
+        let response = ApiError::Http(err).into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_GATEWAY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("HTTP error"), "{}", body);
+        assert!(!body.contains("SECRET"), "{}", body);
+    }
+
+    #[test]
+    fn flare_flux_for_class() {
+        let cases = [
+            ("X1.2", Some(1.2e-4)),
+            ("X", Some(1e-4)),
+            ("M5", Some(5e-5)),
+            ("M8.8", Some(8.8e-5)),
+            ("m5", Some(5e-5)),
+            ("x1.2", Some(1.2e-4)),
+            (" C3.4 ", Some(3.4e-6)),
+            ("B1", Some(1e-7)),
+            ("A9.9", Some(9.9e-8)),
+            ("", None),
+            ("  ", None),
+            ("Z1", None),
+            ("1.2", None),
+            ("M0", None),
+            ("M-1", None),
+            ("Mx", None),
+            ("M5.5.5", None),
+            ("M 5", None),
+        ];
+        for (class, expected) in cases {
+            let flux = Flare::flux_for_class(class);
+            match (flux, expected) {
+                (Some(got), Some(want)) => assert!((got - want).abs() < want * 1e-9, "{:?}: {}", class, got),
+                _ => assert_eq!(flux, expected, "{:?}", class),
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::config::EventBusKind;
+
+pub const TOPIC_ISS: &str = "iss";
+pub const TOPIC_OSDR: &str = "osdr";
+
+// Источники space_cache публикуются в топики space:<source>
+pub const TOPICS: [&str; 7] = [
+    TOPIC_ISS,
+    TOPIC_OSDR,
+    "space:apod",
+    "space:neo",
+    "space:flr",
+    "space:cme",
+    "space:spacex",
+];
+
+const NOTIFY_CHANNEL: &str = "rust_iss_events";
+const BUS_CAPACITY: usize = 1024;
+const LISTENER_RETRY: Duration = Duration::from_secs(5);
+
+// Уведомление об изменении. Сами данные не передаются (NOTIFY ограничен
+// 8000 байтами): клиент получает id записи и при необходимости дочитывает её
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    pub topic: String,
+    pub id: Option<i64>,
+    pub at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl ChangeEvent {
+    pub fn new(topic: impl Into<String>, id: Option<i64>, data: Value) -> Self {
+        Self {
+            topic: topic.into(),
+            id,
+            at: Utc::now(),
+            data,
+        }
+    }
+}
+
+pub fn space_topic(source: &str) -> String {
+    format!("space:{}", source)
+}
+
+// Шина событий. Подписчики всегда читают локальный broadcast-канал;
+// в режиме postgres публикация идёт через pg_notify, а в канал события
+// попадают из LISTEN, поэтому их видят все реплики rust_iss
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChangeEvent>,
+    pool: PgPool,
+    kind: EventBusKind,
+}
+
+impl EventBus {
+    pub fn new(pool: PgPool, kind: EventBusKind) -> Self {
+        let (sender, _) = broadcast::channel(BUS_CAPACITY);
+        Self { sender, pool, kind }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    // Ошибки публикации только логируются: запись в БД уже состоялась
+    pub async fn publish(&self, event: ChangeEvent) {
+        match self.kind {
+            EventBusKind::Local => {
+                // Ошибка означает лишь отсутствие подписчиков
+                let _ = self.sender.send(event);
+            }
+            EventBusKind::Postgres => {
+                let payload = match serde_json::to_string(&event) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        error!("cannot serialize event for '{}': {:?}", event.topic, e);
+                        return;
+                    }
+                };
+                let result = sqlx::query("SELECT pg_notify($1, $2)")
+                    .bind(NOTIFY_CHANNEL)
+                    .bind(payload)
+                    .execute(&self.pool)
+                    .await;
+                if let Err(e) = result {
+                    error!("cannot publish event for '{}': {:?}", event.topic, e);
+                }
+            }
+        }
+    }
+
+    // Для postgres запускает LISTEN на отдельном соединении вне пула,
+    // чтобы слушатель не занимал соединение у задач и запросов
+    pub fn start(&self, database_url: &str, stop: CancellationToken) {
+        if self.kind != EventBusKind::Postgres {
+            return;
+        }
+        let sender = self.sender.clone();
+        let database_url = database_url.to_string();
+
+        tokio::spawn(async move {
+            while !stop.is_cancelled() {
+                if let Err(e) = listen(&database_url, &sender, &stop).await {
+                    warn!("event listener disconnected: {:?}, retrying in {:?}", e, LISTENER_RETRY);
+                    tokio::select! {
+                        _ = stop.cancelled() => break,
+                        _ = tokio::time::sleep(LISTENER_RETRY) => {}
+                    }
+                }
+            }
+        });
+    }
+}
+
+async fn listen(
+    database_url: &str,
+    sender: &broadcast::Sender<ChangeEvent>,
+    stop: &CancellationToken,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect(database_url).await?;
+    listener.listen(NOTIFY_CHANNEL).await?;
+    info!("listening for events on '{}'", NOTIFY_CHANNEL);
+
+    loop {
+        let notification = tokio::select! {
+            _ = stop.cancelled() => return Ok(()),
+            notification = listener.recv() => notification?,
+        };
+        match serde_json::from_str::<ChangeEvent>(notification.payload()) {
+            Ok(event) => {
+                let _ = sender.send(event);
+            }
+            Err(e) => warn!("ignoring malformed event: {:?}", e),
+        }
+    }
+}
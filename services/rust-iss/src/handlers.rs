@@ -1,28 +1,41 @@
 use std::collections::HashMap;
+use std::convert::Infallible;
+use axum::extract::ws::WebSocketUpgrade;
 use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
 use axum::Json;
+use futures::{Stream, StreamExt};
 use serde_json::Value;
 
 use crate::domain::{ApiError, Health};
-use crate::services::{IssService, OsdrService, SpaceCacheService};
+use crate::jobs::{space_jobs, IssFetchJob, OsdrSyncJob};
+use crate::scheduler::{redact_api_key, run_exclusive, Job, RunOutput, TRIGGER_MANUAL};
+use crate::services::{
+    DonkiService, IssService, JobService, NeoService, OrbitService, OsdrService, RetentionService,
+    SpaceCacheService,
+};
 use crate::validation::Validator;
 
 pub type AppState = crate::state::AppState;
 
-pub async fn health() -> Json<Health> {
+pub async fn health(State(state): State<AppState>) -> Json<Health> {
     Json(Health {
         status: "ok",
         now: chrono::Utc::now(),
+        nasa_keys: state.nasa_client.keys.quotas(),
     })
 }
 
 pub async fn last_iss(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
-    let service = IssService::new(state.pool.clone(), state.iss_client.clone());
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
     match service.get_last().await? {
         Some(log) => Ok(Json(serde_json::json!({
             "id": log.id,
             "fetched_at": log.fetched_at,
             "source_url": log.source_url,
+            "position": log.position,
             "payload": log.payload,
         }))),
         None => Ok(Json(serde_json::json!({"message": "no data"}))),
@@ -30,32 +43,251 @@ pub async fn last_iss(State(state): State<AppState>) -> Result<Json<Value>, ApiE
 }
 
 pub async fn trigger_iss(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
-    let service = IssService::new(state.pool.clone(), state.iss_client.clone());
-    service.fetch_and_store().await?;
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+    manual(&state.pool, &IssFetchJob, service.fetch_and_store()).await?;
     last_iss(State(state)).await
 }
 
-pub async fn iss_trend(State(state): State<AppState>) -> Result<Json<crate::domain::IssTrend>, ApiError> {
-    let service = IssService::new(state.pool.clone(), state.iss_client.clone());
-    Ok(Json(service.calculate_trend().await?))
+// Ручной запуск под advisory lock задачи job: пока идёт плановый запуск - 409
+async fn manual<F, T>(pool: &sqlx::PgPool, job: &dyn Job, run: F) -> Result<T, ApiError>
+where
+    F: std::future::Future<Output = Result<T, ApiError>>,
+    T: Into<RunOutput> + Copy,
+{
+    run_exclusive(pool, job.lock_key(), job.name(), TRIGGER_MANUAL, run).await
+}
+
+pub async fn iss_trend(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+    
+    // Без window - сравнение двух последних замеров, как раньше
+    match params.get("window") {
+        Some(window) => {
+            let window = Validator::validate_window(window)?;
+            let expected = std::time::Duration::from_secs(state.config.fetch_intervals.iss);
+            let trend = service.calculate_window_trend(window, expected).await?;
+            Ok(Json(serde_json::json!(trend)))
+        }
+        None => Ok(Json(serde_json::json!(service.calculate_trend().await?))),
+    }
+}
+
+// SSE: событие на каждую новую запись iss_fetch_log. Переподключившийся
+// клиент передаёт Last-Event-ID (или ?last_event_id=) и получает пропущенное
+pub async fn iss_stream(
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .or(params.get("last_event_id").map(String::as_str));
+    let after_id = Validator::validate_last_event_id(last_event_id)?;
+    
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+    let events = service.stream(after_id, state.shutdown.clone()).map(|log| {
+        let data = serde_json::json!({
+            "id": log.id,
+            "fetched_at": log.fetched_at,
+            "position": log.position,
+        });
+        Ok(Event::default()
+            .id(log.id.to_string())
+            .event("position")
+            .data(data.to_string()))
+    });
+    
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+pub async fn iss_history(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<crate::domain::IssHistory>, ApiError> {
+    let (from, to) = Validator::validate_time_range(params.get("from"), params.get("to"))?;
+    let max_points = Validator::validate_max_points(params.get("max_points"))?;
+    
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+    Ok(Json(service.history(from, to, max_points).await?))
+}
+
+pub async fn iss_hourly(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let (from, to) = Validator::validate_time_range(params.get("from"), params.get("to"))?;
+    
+    let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+    let hours = service.hourly(from, to).await?;
+    Ok(Json(serde_json::json!({ "from": from, "to": to, "hours": hours })))
+}
+
+pub async fn iss_predict(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<crate::domain::IssPrediction>, ApiError> {
+    let (from, to, step) = Validator::validate_prediction_range(
+        params.get("from"),
+        params.get("to"),
+        params.get("step"),
+    )?;
+    
+    let service = OrbitService::new(state.pool.clone(), state.tle_client.clone());
+    Ok(Json(service.predict(from, to, step).await?))
+}
+
+pub async fn iss_passes(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<crate::domain::IssPasses>, ApiError> {
+    let observer = Validator::validate_observer(
+        params.get("lat"),
+        params.get("lon"),
+        params.get("alt"),
+    )?;
+    let (days, min_elevation) = Validator::validate_pass_window(
+        params.get("days"),
+        params.get("min_elevation"),
+    )?;
+    
+    let service = OrbitService::new(state.pool.clone(), state.tle_client.clone());
+    Ok(Json(service.passes(observer, days, min_elevation).await?))
 }
 
 pub async fn osdr_sync(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
-    let service = OsdrService::new(state.pool.clone(), state.nasa_client.clone());
-    let written = service.sync().await?;
-    Ok(Json(serde_json::json!({ "written": written })))
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    let report = manual(&state.pool, &OsdrSyncJob, service.sync()).await?;
+    Ok(Json(serde_json::json!({
+        "written": report.written(),
+        "inserted": report.inserted,
+        "updated": report.updated,
+        "unchanged": report.unchanged,
+        "missing": report.missing,
+        "failed": report.failed,
+        "skipped": report.skipped,
+    })))
+}
+
+pub async fn osdr_search(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<crate::domain::OsdrSearchPage>, ApiError> {
+    let query = Validator::validate_osdr_search(&params)?;
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    Ok(Json(service.search(&query).await?))
+}
+
+pub async fn osdr_detail(
+    Path(dataset_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    let (item, versions, last_changed_at) = service.get(&dataset_id).await?;
+    Ok(Json(serde_json::json!({
+        "item": item,
+        "versions": versions,
+        "last_changed_at": last_changed_at,
+    })))
+}
+
+pub async fn osdr_mapping(State(state): State<AppState>) -> Json<crate::mapping::OsdrMapping> {
+    Json(state.config.osdr_mapping.as_ref().clone())
+}
+
+pub async fn osdr_mapping_dry_run(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<Json<Value>, ApiError> {
+    let (items, profile) = Validator::validate_mapping_dry_run(body)?;
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    Ok(Json(service.dry_run_mapping(&items, profile.as_ref())))
+}
+
+pub async fn osdr_files(
+    Path(dataset_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    let files = service.files(&dataset_id).await?;
+    Ok(Json(serde_json::json!({
+        "dataset_id": dataset_id,
+        "files": files,
+    })))
+}
+
+// Версии от новых к старым; next_before передаётся как before для следующей страницы
+pub async fn osdr_history(
+    Path(dataset_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let limit = Validator::validate_limit_param(&params)?;
+    let before = match params.get("before") {
+        Some(b) => Some(b.parse::<i32>().ok().filter(|v| *v > 0)
+            .ok_or_else(|| ApiError::Validation("before must be a positive version number".to_string()))?),
+        None => None,
+    };
+    let with_raw = params.get("raw").is_some_and(|v| v == "true" || v == "1");
+    
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
+    let versions = service.history(&dataset_id, before, limit, with_raw).await?;
+    let next_before = match versions.last() {
+        Some(last) if versions.len() as i64 == limit && last.version > 1 => Some(last.version),
+        _ => None,
+    };
+    Ok(Json(serde_json::json!({
+        "dataset_id": dataset_id,
+        "versions": versions,
+        "next_before": next_before,
+    })))
 }
 
 pub async fn osdr_list(
     Query(params): Query<HashMap<String, String>>,
     State(state): State<AppState>,
 ) -> Result<Json<Value>, ApiError> {
-    let limit_str = params.get("limit").cloned().unwrap_or_else(|| "20".to_string());
-    let limit = limit_str.parse::<i64>()
-        .map_err(|_| ApiError::Validation("invalid limit parameter".to_string()))?;
-    let limit = Validator::validate_limit(Some(limit))?;
+    let limit = Validator::validate_limit_param(&params)?;
     
-    let service = OsdrService::new(state.pool.clone(), state.nasa_client.clone());
+    let service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
     let items = service.list(limit).await?;
     
     let out: Vec<Value> = items.into_iter().map(|item| {
@@ -66,6 +298,8 @@ pub async fn osdr_list(
             "status": item.status,
             "updated_at": item.updated_at,
             "inserted_at": item.inserted_at,
+            "missing_since": item.missing_since,
+            "details_fetched_at": item.details_fetched_at,
             "raw": item.raw,
         })
     }).collect();
@@ -83,12 +317,14 @@ pub async fn space_latest(
         state.pool.clone(),
         state.nasa_client.clone(),
         state.spacex_client.clone(),
+        state.events.clone(),
     );
     
     match service.get_latest(&src).await? {
         Some(cache) => Ok(Json(serde_json::json!({
             "source": cache.source,
             "fetched_at": cache.fetched_at,
+            "last_seen_at": cache.last_seen_at,
             "payload": cache.payload,
         }))),
         None => Ok(Json(serde_json::json!({
@@ -98,61 +334,138 @@ pub async fn space_latest(
     }
 }
 
-pub async fn space_refresh(
-    Query(q): Query<HashMap<String, String>>,
+// История снимков источника от новых к старым: ?from=&to=&cursor=&limit=&fields=
+pub async fn space_history(
+    Path(src): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
     State(state): State<AppState>,
-) -> Result<Json<Value>, ApiError> {
-    let list = q.get("src").cloned().unwrap_or_else(|| "apod,neo,flr,cme,spacex".to_string());
-    let mut done = Vec::new();
+) -> Result<Json<crate::domain::SpaceCachePage>, ApiError> {
+    Validator::validate_source(&src)?;
+    let from = Validator::validate_time(params.get("from"), "from")?;
+    let to = Validator::validate_time(params.get("to"), "to")?;
+    let after = Validator::validate_cursor(params.get("cursor"))?;
+    let fields = Validator::validate_fields(params.get("fields"))?;
+    let limit = Validator::validate_limit_param(&params)?;
     
     let service = SpaceCacheService::new(
         state.pool.clone(),
         state.nasa_client.clone(),
         state.spacex_client.clone(),
+        state.events.clone(),
     );
+    Ok(Json(service.history(&src, (from, to), after, limit, &fields).await?))
+}
+
+pub async fn space_snapshot(
+    Path((src, id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<crate::domain::SpaceCache>, ApiError> {
+    Validator::validate_source(&src)?;
+    let id = id.parse::<i64>()
+        .map_err(|_| ApiError::Validation("id must be an integer".to_string()))?;
+    let fields = Validator::validate_fields(params.get("fields"))?;
     
-    for s in list.split(',').map(|x| x.trim().to_lowercase()) {
-        match s.as_str() {
-            "apod" => {
-                if let Err(e) = service.fetch_apod().await {
-                    tracing::error!("Failed to fetch apod: {:?}", e);
-                } else {
-                    done.push("apod");
-                }
-            }
-            "neo" => {
-                if let Err(e) = service.fetch_neo().await {
-                    tracing::error!("Failed to fetch neo: {:?}", e);
-                } else {
-                    done.push("neo");
-                }
-            }
-            "flr" => {
-                if let Err(e) = service.fetch_donki_flr().await {
-                    tracing::error!("Failed to fetch flr: {:?}", e);
-                } else {
-                    done.push("flr");
-                }
-            }
-            "cme" => {
-                if let Err(e) = service.fetch_donki_cme().await {
-                    tracing::error!("Failed to fetch cme: {:?}", e);
-                } else {
-                    done.push("cme");
+    let service = SpaceCacheService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.spacex_client.clone(),
+        state.events.clone(),
+    );
+    Ok(Json(service.get_snapshot(&src, id, &fields).await?))
+}
+
+// Сближения астероидов: ?hazardous=true&from=&to=&max_miss_km=&sort=miss&limit=
+pub async fn neo_list(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let query = Validator::validate_neo_query(&params)?;
+    
+    let service = NeoService::new(state.pool.clone());
+    let approaches = service.approaches(&query).await?;
+    Ok(Json(serde_json::json!({
+        "from": query.from,
+        "to": query.to,
+        "count": approaches.len(),
+        "approaches": approaches,
+    })))
+}
+
+pub async fn flares_list(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let query = Validator::validate_flare_query(&params)?;
+    let flares = DonkiService::new(state.pool.clone()).flares(&query).await?;
+    Ok(Json(serde_json::json!({ "from": query.from, "to": query.to, "flares": flares })))
+}
+
+// Вспышка и связанные с ней CME (по linkedEvents DONKI)
+pub async fn flare_detail(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let (flare, cmes) = DonkiService::new(state.pool.clone()).flare(&id).await?;
+    Ok(Json(serde_json::json!({ "flare": flare, "cmes": cmes })))
+}
+
+pub async fn cmes_list(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let query = Validator::validate_cme_query(&params)?;
+    let cmes = DonkiService::new(state.pool.clone()).cmes(&query).await?;
+    Ok(Json(serde_json::json!({ "from": query.from, "to": query.to, "cmes": cmes })))
+}
+
+pub async fn cme_detail(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let (cme, flares) = DonkiService::new(state.pool.clone()).cme(&id).await?;
+    Ok(Json(serde_json::json!({ "cme": cme, "flares": flares })))
+}
+
+pub async fn space_refresh(
+    Query(q): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let list = q.get("src").cloned().unwrap_or_else(|| "apod,neo,flr,cme,spacex".to_string());
+    let sources: Vec<String> = list.split(',').map(|x| x.trim().to_lowercase()).collect();
+    let mut done = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut failed = Vec::new();
+    let mut last_error = None;
+    
+    // Каждая задача - один запуск в job_runs, как у планировщика; flr и cme
+    // обновляются вместе и в ответе названы donki
+    for job in space_jobs(sources.iter().map(String::as_str)) {
+        let s = job.name().to_string();
+        let result = manual(&state.pool, job, job.run(&state)).await;
+        
+        // Источник с тем же содержимым считается обновлённым, но отмечается отдельно
+        match result {
+            Ok(output) => {
+                if output.written == 0 && output.unchanged > 0 {
+                    unchanged.push(s.clone());
                 }
+                done.push(s);
             }
-            "spacex" => {
-                if let Err(e) = service.fetch_spacex().await {
-                    tracing::error!("Failed to fetch spacex: {:?}", e);
-                } else {
-                    done.push("spacex");
-                }
+            Err(e) => {
+                failed.push(serde_json::json!({ "src": s, "error": redact_api_key(&e.to_string()) }));
+                last_error = Some(e);
             }
-            _ => {}
         }
     }
     
-    Ok(Json(serde_json::json!({ "refreshed": done })))
+    // Не обновился ни один источник - ошибка последнего с его кодом ответа
+    if done.is_empty() {
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+    }
+    Ok(Json(serde_json::json!({ "refreshed": done, "unchanged": unchanged, "failed": failed })))
 }
 
 pub async fn space_summary(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
@@ -160,6 +473,7 @@ pub async fn space_summary(State(state): State<AppState>) -> Result<Json<Value>,
         state.pool.clone(),
         state.nasa_client.clone(),
         state.spacex_client.clone(),
+        state.events.clone(),
     );
     
     let apod = service.get_latest("apod").await?
@@ -182,12 +496,17 @@ pub async fn space_summary(State(state): State<AppState>) -> Result<Json<Value>,
         .map(|c| serde_json::json!({"at": c.fetched_at, "payload": c.payload}))
         .unwrap_or(serde_json::json!({}));
     
-    let iss_service = IssService::new(state.pool.clone(), state.iss_client.clone());
+    let iss_service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
     let iss_last = iss_service.get_last().await?
         .map(|l| serde_json::json!({"at": l.fetched_at, "payload": l.payload}))
         .unwrap_or(serde_json::json!({}));
     
-    let osdr_service = OsdrService::new(state.pool.clone(), state.nasa_client.clone());
+    let osdr_service = OsdrService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.events.clone(),
+        state.config.osdr_mapping.clone(),
+    );
     let osdr_count = osdr_service.count().await?;
     
     Ok(Json(serde_json::json!({
@@ -201,3 +520,49 @@ pub async fn space_summary(State(state): State<AppState>) -> Result<Json<Value>,
     })))
 }
 
+
+pub async fn jobs_list(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let service = JobService::new(state.pool.clone(), state.jobs.clone());
+    let jobs = service.statuses().await?;
+    Ok(Json(serde_json::json!({ "jobs": jobs })))
+}
+
+pub async fn job_runs(
+    Path(name): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let limit = Validator::validate_limit_param(&params)?;
+    
+    let service = JobService::new(state.pool.clone(), state.jobs.clone());
+    let runs = service.runs(&name, limit).await?;
+    Ok(Json(serde_json::json!({ "job": name, "runs": runs })))
+}
+
+// Отчёт политики хранения: последние записи retention_log
+pub async fn retention_log(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let limit = Validator::validate_limit_param(&params)?;
+    
+    let service = RetentionService::new(state.pool.clone(), state.config.retention.clone());
+    let entries = service.recent(limit).await?;
+    Ok(Json(serde_json::json!({ "entries": entries })))
+}
+
+// WebSocket с подпиской на топики: ?topics=iss,space:neo при подключении
+// или сообщениями {"action": "subscribe", "topics": [...]}
+pub async fn ws_connect(
+    ws: WebSocketUpgrade,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Response, ApiError> {
+    let requested: Vec<String> = params
+        .get("topics")
+        .map(|t| t.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    let topics = Validator::validate_topics(&requested)?.into_iter().collect();
+    
+    Ok(ws.on_upgrade(move |socket| crate::ws::session(socket, state, topics)))
+}
@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+
+use crate::config::Config;
+use crate::domain::ApiError;
+use crate::scheduler::{Job, RunOutput, Schedule, Scheduler};
+use crate::services::{IssService, OrbitService, OsdrService, RetentionService, SpaceCacheService};
+use crate::state::AppState;
+
+pub struct OsdrSyncJob;
+
+#[async_trait]
+impl Job for OsdrSyncJob {
+    fn name(&self) -> &'static str {
+        "osdr"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1001
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        let service = OsdrService::new(
+            state.pool.clone(),
+            state.nasa_client.clone(),
+            state.events.clone(),
+            state.config.osdr_mapping.clone(),
+        );
+        Ok(service.sync().await?.into())
+    }
+}
+
+pub struct IssFetchJob;
+
+#[async_trait]
+impl Job for IssFetchJob {
+    fn name(&self) -> &'static str {
+        "iss"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1002
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        let service = IssService::new(state.pool.clone(), state.iss_client.clone(), state.events.clone());
+        Ok(service.fetch_and_store().await?.into())
+    }
+}
+
+pub struct ApodJob;
+
+#[async_trait]
+impl Job for ApodJob {
+    fn name(&self) -> &'static str {
+        "apod"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1003
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        space_service(state).fetch_apod().await
+    }
+}
+
+pub struct NeoJob;
+
+#[async_trait]
+impl Job for NeoJob {
+    fn name(&self) -> &'static str {
+        "neo"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1004
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        space_service(state).fetch_neo().await
+    }
+}
+
+pub struct DonkiJob;
+
+#[async_trait]
+impl Job for DonkiJob {
+    fn name(&self) -> &'static str {
+        "donki"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1005
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        let service = space_service(state);
+        // CME запрашиваем даже если FLR упал
+        let flr = service.fetch_donki_flr().await;
+        let cme = service.fetch_donki_cme().await;
+        Ok(flr? + cme?)
+    }
+}
+
+pub struct SpaceXJob;
+
+#[async_trait]
+impl Job for SpaceXJob {
+    fn name(&self) -> &'static str {
+        "spacex"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1006
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        space_service(state).fetch_spacex().await
+    }
+}
+
+pub struct TleFetchJob;
+
+#[async_trait]
+impl Job for TleFetchJob {
+    fn name(&self) -> &'static str {
+        "tle"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1007
+    }
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        let service = OrbitService::new(state.pool.clone(), state.tle_client.clone());
+        Ok(service.fetch_and_store_tle().await?.into())
+    }
+}
+
+pub struct RetentionJob;
+
+#[async_trait]
+impl Job for RetentionJob {
+    fn name(&self) -> &'static str {
+        "retention"
+    }
+
+    fn lock_key(&self) -> i64 {
+        1008
+    }
+
+    // В rows_written попадает общее число удалённых строк
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError> {
+        let service = RetentionService::new(state.pool.clone(), state.config.retention.clone());
+        Ok(service.run(false).await?.deleted().into())
+    }
+}
+
+// Задача, обновляющая источник space_cache. FLR и CME - одна задача donki,
+// поэтому ручное обновление любого из них запускает её целиком
+pub fn space_job(source: &str) -> Option<&'static dyn Job> {
+    match source {
+        "apod" => Some(&ApodJob),
+        "neo" => Some(&NeoJob),
+        "flr" | "cme" => Some(&DonkiJob),
+        "spacex" => Some(&SpaceXJob),
+        _ => None,
+    }
+}
+
+// Задачи для списка источников без повторов (flr,cme -> donki один раз)
+pub fn space_jobs<'a>(sources: impl IntoIterator<Item = &'a str>) -> Vec<&'static dyn Job> {
+    let mut jobs: Vec<&'static dyn Job> = Vec::new();
+    for job in sources.into_iter().filter_map(space_job) {
+        if !jobs.iter().any(|j| j.name() == job.name()) {
+            jobs.push(job);
+        }
+    }
+    jobs
+}
+
+pub fn register_all(scheduler: &mut Scheduler, config: &Config) -> Result<(), ApiError> {
+    config.check_db_pool()?;
+    let intervals = &config.fetch_intervals;
+    let crons = &config.fetch_crons;
+
+    scheduler
+        .register(OsdrSyncJob, Schedule::from_config(crons.osdr.as_deref(), intervals.osdr)?)?
+        .register(IssFetchJob, Schedule::from_config(crons.iss.as_deref(), intervals.iss)?)?
+        .register(ApodJob, Schedule::from_config(crons.apod.as_deref(), intervals.apod)?)?
+        .register(NeoJob, Schedule::from_config(crons.neo.as_deref(), intervals.neo)?)?
+        .register(DonkiJob, Schedule::from_config(crons.donki.as_deref(), intervals.donki)?)?
+        .register(SpaceXJob, Schedule::from_config(crons.spacex.as_deref(), intervals.spacex)?)?
+        .register(TleFetchJob, Schedule::from_config(crons.tle.as_deref(), intervals.tle)?)?
+        .register(RetentionJob, Schedule::from_config(crons.retention.as_deref(), intervals.retention)?)?;
+
+    Ok(())
+}
+
+fn space_service(state: &AppState) -> SpaceCacheService {
+    SpaceCacheService::new(
+        state.pool.clone(),
+        state.nasa_client.clone(),
+        state.spacex_client.clone(),
+        state.events.clone(),
+    )
+}
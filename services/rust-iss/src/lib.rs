@@ -0,0 +1,19 @@
+// Общая часть сервиса и CLI-утилиты rust_iss-admin
+pub mod config;
+pub mod domain;
+pub mod repo;
+pub mod clients;
+pub mod services;
+pub mod handlers;
+pub mod routes;
+pub mod state;
+pub mod validation;
+pub mod middleware;
+pub mod scheduler;
+pub mod jobs;
+pub mod orbit;
+pub mod events;
+pub mod ws;
+pub mod migrate;
+pub mod diff;
+pub mod mapping;
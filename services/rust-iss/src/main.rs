@@ -1,25 +1,17 @@
-mod config;
-mod domain;
-mod repo;
-mod clients;
-mod services;
-mod handlers;
-mod routes;
-mod state;
-mod validation;
-mod middleware;
-
+use std::future::IntoFuture;
 use std::time::Duration;
 use sqlx::postgres::PgPoolOptions;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use axum::middleware::from_fn_with_state;
 
-use crate::config::Config;
-use crate::repo::init_db;
-use crate::routes::create_router;
-use crate::services::{IssService, OsdrService, SpaceCacheService};
-use crate::state::AppState;
+use rust_iss::config::Config;
+use rust_iss::repo::JobRunRepo;
+use rust_iss::routes::create_router;
+use rust_iss::scheduler::Scheduler;
+use rust_iss::state::AppState;
+use rust_iss::{jobs, middleware, migrate};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -32,198 +24,53 @@ async fn main() -> anyhow::Result<()> {
 
     let config = Config::from_env();
     let pool = PgPoolOptions::new()
-        .max_connections(5)
+        .max_connections(config.db_max_connections)
         .connect(&config.database_url)
         .await?;
     
-    init_db(&pool).await?;
+    // --migrate-only: только применить миграции и выйти (отдельный шаг деплоя)
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        let applied = migrate::run(&pool).await?;
+        info!("{} migration(s) applied", applied);
+        pool.close().await;
+        return Ok(());
+    }
 
-    let state = AppState::new(pool.clone(), config.clone())?;
+    if config.migrate_on_start {
+        migrate::run(&pool).await?;
+    } else {
+        migrate::ensure_applied(&pool).await?;
+    }
 
     // Background tasks with advisory locks to prevent overlapping
-    let osdr_interval = config.fetch_intervals.osdr;
-    let iss_interval = config.fetch_intervals.iss;
-    let apod_interval = config.fetch_intervals.apod;
-    let neo_interval = config.fetch_intervals.neo;
-    let donki_interval = config.fetch_intervals.donki;
-    let spacex_interval = config.fetch_intervals.spacex;
-    
-    // OSDR background task
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            loop {
-                // Use PostgreSQL advisory lock to prevent overlapping
-                let lock_key: i64 = 1001; // Unique key for OSDR
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = OsdrService::new(state.pool.clone(), state.nasa_client.clone());
-                    if let Err(e) = service.sync().await {
-                        error!("OSDR sync error: {:?}", e);
-                    }
-                    // Разблокируем только если блокировка была получена
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(osdr_interval)).await;
-            }
-        });
-    }
-    
-    // ISS background task
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            loop {
-                let lock_key: i64 = 1002; // Unique key for ISS
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = IssService::new(state.pool.clone(), state.iss_client.clone());
-                    if let Err(e) = service.fetch_and_store().await {
-                        error!("ISS fetch error: {:?}", e);
-                    }
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(iss_interval)).await;
-            }
-        });
-    }
-    
-    // APOD background task
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            loop {
-                let lock_key: i64 = 1003;
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = SpaceCacheService::new(
-                        state.pool.clone(),
-                        state.nasa_client.clone(),
-                        state.spacex_client.clone(),
-                    );
-                    if let Err(e) = service.fetch_apod().await {
-                        error!("APOD fetch error: {:?}", e);
-                    }
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(apod_interval)).await;
-            }
-        });
-    }
-    
-    // NEO background task
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            loop {
-                let lock_key: i64 = 1004;
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = SpaceCacheService::new(
-                        state.pool.clone(),
-                        state.nasa_client.clone(),
-                        state.spacex_client.clone(),
-                    );
-                    if let Err(e) = service.fetch_neo().await {
-                        error!("NEO fetch error: {:?}", e);
-                    }
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(neo_interval)).await;
-            }
-        });
-    }
-    
-    // DONKI background task
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            loop {
-                let lock_key: i64 = 1005;
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = SpaceCacheService::new(
-                        state.pool.clone(),
-                        state.nasa_client.clone(),
-                        state.spacex_client.clone(),
-                    );
-                    let _ = service.fetch_donki_flr().await;
-                    let _ = service.fetch_donki_cme().await;
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(donki_interval)).await;
-            }
-        });
+    let mut scheduler = Scheduler::new(config.job_jitter, config.job_concurrency);
+    jobs::register_all(&mut scheduler, &config)?;
+
+    match JobRunRepo::interrupt_stale(&pool, &scheduler.lock_keys()).await {
+        Ok(0) => {}
+        Ok(count) => warn!("{} job run(s) left 'running' by a previous process marked as interrupted", count),
+        Err(e) => error!("cannot clean up interrupted job runs: {:?}", e),
     }
-    
-    // SpaceX background task
+
+    // shutdown: перестаём принимать запросы и планировать задачи,
+    // abort: по истечении SHUTDOWN_TIMEOUT_SECONDS прерываем незавершённые
+    let shutdown = CancellationToken::new();
+    let abort = CancellationToken::new();
+
+    let state = AppState::new(pool.clone(), config.clone(), scheduler.catalog(), shutdown.clone())?;
+    state.events.start(&config.database_url, shutdown.clone());
+    let jobs = scheduler.start(state.clone(), shutdown.clone(), abort.clone());
+
     {
-        let state = state.clone();
+        let shutdown = shutdown.clone();
+        let abort = abort.clone();
+        let timeout = config.shutdown_timeout;
         tokio::spawn(async move {
-            loop {
-                let lock_key: i64 = 1006;
-                if let Ok(Some(true)) = sqlx::query_scalar::<_, bool>(
-                    "SELECT pg_try_advisory_lock($1)"
-                )
-                .bind(lock_key)
-                .fetch_optional(&state.pool)
-                .await
-                {
-                    let service = SpaceCacheService::new(
-                        state.pool.clone(),
-                        state.nasa_client.clone(),
-                        state.spacex_client.clone(),
-                    );
-                    if let Err(e) = service.fetch_spacex().await {
-                        error!("SpaceX fetch error: {:?}", e);
-                    }
-                    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
-                        .bind(lock_key)
-                        .execute(&state.pool)
-                        .await;
-                }
-                tokio::time::sleep(Duration::from_secs(spacex_interval)).await;
-            }
+            shutdown_signal().await;
+            info!("shutdown requested, draining for up to {:?}", timeout);
+            shutdown.cancel();
+            tokio::time::sleep(timeout).await;
+            abort.cancel();
         });
     }
 
@@ -236,6 +83,52 @@ async fn main() -> anyhow::Result<()> {
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     info!("rust_iss listening on 0.0.0.0:3000");
-    axum::serve(listener, app.into_make_service()).await?;
+    let server = axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+        .into_future();
+
+    tokio::select! {
+        result = server => result?,
+        _ = abort.cancelled() => warn!("HTTP requests still in flight after shutdown deadline"),
+    }
+
+    // Прерванные задачи снимают advisory lock сами, поэтому ждём их и после abort
+    jobs.wait().await;
+    info!("background jobs stopped");
+
+    if tokio::time::timeout(Duration::from_secs(5), pool.close()).await.is_err() {
+        warn!("database pool did not close in time");
+    }
+    info!("rust_iss stopped");
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("cannot listen for SIGINT: {:?}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                error!("cannot listen for SIGTERM: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
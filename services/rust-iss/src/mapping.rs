@@ -0,0 +1,359 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::OsdrRecord;
+
+const BUILTIN_OSDR: &str = include_str!("../mappings/osdr.toml");
+
+// Профиль разбора датасета OSDR в колонки osdr_items: для каждого поля -
+// JSON Pointer'ы по порядку и приведение типа. Файл .toml или .json
+// (OSDR_MAPPING_FILE), без него - встроенный mappings/osdr.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OsdrMapping {
+    pub dataset_id: FieldRule,
+    pub title: FieldRule,
+    pub status: FieldRule,
+    pub updated_at: FieldRule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldRule {
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub coerce: Coercion,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Coercion {
+    // Число и bool - текстом, пустая строка - нет значения
+    #[default]
+    String,
+    Lowercase,
+    // RFC 3339 или formats; число - секунды Unix
+    Datetime,
+    EpochMillis,
+}
+
+enum Coerced {
+    Text(String),
+    Time(DateTime<Utc>),
+}
+
+impl Coerced {
+    fn to_json(&self) -> Value {
+        match self {
+            Coerced::Text(s) => Value::String(s.clone()),
+            Coerced::Time(t) => Value::String(t.to_rfc3339()),
+        }
+    }
+}
+
+impl OsdrMapping {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_OSDR, false).expect("built-in mappings/osdr.toml is invalid")
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text, path.ends_with(".json"))
+    }
+
+    pub fn parse(text: &str, json: bool) -> Result<Self, String> {
+        let mapping: Self = if json {
+            serde_json::from_str(text).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(text).map_err(|e| e.to_string())?
+        };
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    // Профиль из JSON-значения, например из тела запроса
+    pub fn from_value(value: Value) -> Result<Self, String> {
+        let mapping: Self = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    fn fields(&self) -> [(&'static str, &FieldRule); 4] {
+        [
+            ("dataset_id", &self.dataset_id),
+            ("title", &self.title),
+            ("status", &self.status),
+            ("updated_at", &self.updated_at),
+        ]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, rule) in self.fields() {
+            if rule.paths.is_empty() {
+                return Err(format!("{}: paths must not be empty", name));
+            }
+            if let Some(path) = rule.paths.iter().find(|p| !p.is_empty() && !p.starts_with('/')) {
+                return Err(format!("{}: '{}' is not a JSON Pointer (must start with '/')", name, path));
+            }
+            let temporal = matches!(rule.coerce, Coercion::Datetime | Coercion::EpochMillis);
+            if temporal != (name == "updated_at") {
+                return Err(format!("{}: coerce '{:?}' does not fit this field", name, rule.coerce));
+            }
+            if !rule.formats.is_empty() && rule.coerce != Coercion::Datetime {
+                return Err(format!("{}: formats apply only to coerce = \"datetime\"", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, item: Value) -> OsdrRecord {
+        OsdrRecord {
+            dataset_id: self.dataset_id.text(&item),
+            title: self.title.text(&item),
+            status: self.status.text(&item),
+            updated_at: self.updated_at.time(&item),
+            raw: item,
+            details_fetched: false,
+        }
+    }
+
+    // Разбор без записи: итоговые значения и по каждому полю - какой путь сработал
+    // и почему отвергнуты предыдущие
+    pub fn dry_run(&self, item: &Value) -> Value {
+        let mut record = serde_json::Map::new();
+        let mut fields = Vec::new();
+        for (name, rule) in self.fields() {
+            let mut rejected = Vec::new();
+            let mut matched = None;
+            for path in &rule.paths {
+                let Some(source) = item.pointer(path) else {
+                    rejected.push(serde_json::json!({ "path": path, "reason": "absent" }));
+                    continue;
+                };
+                match rule.coerce(source) {
+                    Ok(value) => {
+                        matched = Some((path, source, value.to_json()));
+                        break;
+                    }
+                    Err(reason) => rejected.push(serde_json::json!({
+                        "path": path,
+                        "source": source,
+                        "reason": reason,
+                    })),
+                }
+            }
+            let value = matched.as_ref().map(|(_, _, v)| v.clone()).unwrap_or(Value::Null);
+            record.insert(name.to_string(), value.clone());
+            fields.push(serde_json::json!({
+                "field": name,
+                "coerce": rule.coerce,
+                "path": matched.as_ref().map(|(p, _, _)| p),
+                "source": matched.as_ref().map(|(_, s, _)| s),
+                "value": value,
+                "rejected": rejected,
+            }));
+        }
+        serde_json::json!({ "record": record, "fields": fields })
+    }
+}
+
+impl FieldRule {
+    fn first(&self, item: &Value) -> Option<Coerced> {
+        self.paths
+            .iter()
+            .filter_map(|p| item.pointer(p))
+            .find_map(|v| self.coerce(v).ok())
+    }
+
+    fn text(&self, item: &Value) -> Option<String> {
+        match self.first(item)? {
+            Coerced::Text(s) => Some(s),
+            Coerced::Time(_) => None,
+        }
+    }
+
+    fn time(&self, item: &Value) -> Option<DateTime<Utc>> {
+        match self.first(item)? {
+            Coerced::Time(t) => Some(t),
+            Coerced::Text(_) => None,
+        }
+    }
+
+    fn coerce(&self, v: &Value) -> Result<Coerced, &'static str> {
+        match self.coerce {
+            Coercion::String | Coercion::Lowercase => {
+                let text = match v {
+                    Value::String(s) if !s.is_empty() => s.clone(),
+                    Value::String(_) => return Err("empty string"),
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    _ => return Err("not a scalar"),
+                };
+                Ok(Coerced::Text(match self.coerce {
+                    Coercion::Lowercase => text.to_lowercase(),
+                    _ => text,
+                }))
+            }
+            Coercion::Datetime => match v {
+                Value::String(s) => self.parse_datetime(s).map(Coerced::Time).ok_or("unrecognised datetime"),
+                Value::Number(n) => n
+                    .as_i64()
+                    .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+                    .map(Coerced::Time)
+                    .ok_or("timestamp out of range"),
+                _ => Err("not a string or number"),
+            },
+            Coercion::EpochMillis => {
+                let millis = match v {
+                    Value::Number(n) => n.as_i64(),
+                    Value::String(s) => s.trim().parse().ok(),
+                    _ => None,
+                };
+                millis
+                    .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                    .map(Coerced::Time)
+                    .ok_or("not epoch milliseconds")
+            }
+        }
+    }
+
+    fn parse_datetime(&self, s: &str) -> Option<DateTime<Utc>> {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Some(dt.with_timezone(&Utc));
+        }
+        self.formats.iter().find_map(|format| {
+            if let Ok(dt) = DateTime::parse_from_str(s, format) {
+                return Some(dt.with_timezone(&Utc));
+            }
+            if let Ok(ndt) = NaiveDateTime::parse_from_str(s, format) {
+                return Some(Utc.from_utc_datetime(&ndt));
+            }
+            NaiveDate::parse_from_str(s, format)
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|ndt| Utc.from_utc_datetime(&ndt))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const VALID: &str = r#"
+        [dataset_id]
+        paths = ["/id"]
+        [title]
+        paths = ["/title"]
+        [status]
+        paths = ["/status"]
+        coerce = "lowercase"
+        [updated_at]
+        paths = ["/updated"]
+        coerce = "datetime"
+    "#;
+
+    // Профиль VALID с заменой одной секции
+    fn with_section(field: &str, body: &str) -> String {
+        let mut out = String::new();
+        let mut skip = false;
+        for line in VALID.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                skip = line == format!("[{}]", field);
+                if skip && !body.is_empty() {
+                    out.push_str(&format!("[{}]\n{}\n", field, body));
+                }
+            }
+            if !skip {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn builtin_and_valid_profiles_parse() {
+        OsdrMapping::builtin();
+        OsdrMapping::parse(VALID, false).unwrap();
+        let value = serde_json::to_value(OsdrMapping::parse(VALID, false).unwrap()).unwrap();
+        OsdrMapping::parse(&value.to_string(), true).unwrap();
+        OsdrMapping::from_value(value).unwrap();
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        let cases = [
+            ("missing field", with_section("title", ""), "missing field `title`"),
+            ("empty paths", with_section("title", "paths = []"), "title: paths must not be empty"),
+            ("no leading slash", with_section("title", r#"paths = ["title"]"#), "title: 'title' is not a JSON Pointer"),
+            ("dotted path", with_section("status", r#"paths = ["/status", "meta.status"]"#), "status: 'meta.status' is not a JSON Pointer"),
+            ("unknown coercion", with_section("title", "paths = [\"/title\"]\ncoerce = \"number\""), "unknown variant `number`"),
+            ("datetime on text field", with_section("title", "paths = [\"/title\"]\ncoerce = \"datetime\""), "title: coerce 'Datetime' does not fit"),
+            ("text on updated_at", with_section("updated_at", r#"paths = ["/updated"]"#), "updated_at: coerce 'String' does not fit"),
+            (
+                "formats without datetime",
+                with_section("updated_at", "paths = [\"/updated\"]\ncoerce = \"epoch_millis\"\nformats = [\"%Y\"]"),
+                "updated_at: formats apply only to coerce = \"datetime\"",
+            ),
+            ("unknown key", with_section("title", "paths = [\"/title\"]\npath = \"/x\""), "unknown field `path`"),
+            ("unknown field", format!("{}\n[owner]\npaths = [\"/owner\"]", VALID), "unknown field `owner`"),
+        ];
+        for (name, text, expected) in cases {
+            let err = OsdrMapping::parse(&text, false).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", name, err);
+        }
+
+        let err = OsdrMapping::from_value(json!({"dataset_id": {"paths": ["/id"]}})).unwrap_err();
+        assert!(err.contains("missing field `title`"), "{}", err);
+        let err = OsdrMapping::parse("{\"title\": 1}", true).unwrap_err();
+        assert!(err.contains("invalid type"), "{}", err);
+    }
+
+    #[test]
+    fn apply_takes_first_coercible_path() {
+        let mapping = OsdrMapping::builtin();
+        let cases = [
+            (json!({"id": "OSD-1", "title": "T"}), Some("OSD-1"), Some("T"), None),
+            // Пустая строка не значение, число - текстом
+            (json!({"dataset_id": "", "id": 48, "title": "", "name": "N"}), Some("48"), Some("N"), None),
+            (json!({"id": "OSD-2", "updated": "2024-05-01T10:00:00Z"}), Some("OSD-2"), None, Some("2024-05-01T10:00:00+00:00")),
+            (json!({"id": "OSD-3", "updated": "yesterday", "modified": "2024-05-01 10:00:00"}), Some("OSD-3"), None, Some("2024-05-01T10:00:00+00:00")),
+            (json!({"id": "OSD-4", "timestamp": 1714557600}), Some("OSD-4"), None, Some("2024-05-01T10:00:00+00:00")),
+            (json!({"title": {"nested": true}}), None, None, None),
+        ];
+        for (item, id, title, updated) in cases {
+            let record = mapping.apply(item.clone());
+            assert_eq!(record.dataset_id.as_deref(), id, "{}", item);
+            assert_eq!(record.title.as_deref(), title, "{}", item);
+            assert_eq!(record.updated_at.map(|t| t.to_rfc3339()).as_deref(), updated, "{}", item);
+            assert_eq!(record.raw, item);
+        }
+
+        let millis = OsdrMapping::parse(&with_section("updated_at", "paths = [\"/ts\"]\ncoerce = \"epoch_millis\""), false).unwrap();
+        let record = millis.apply(json!({"id": "OSD-5", "status": "PUBLIC", "ts": "1714557600000"}));
+        assert_eq!(record.status.as_deref(), Some("public"));
+        assert_eq!(record.updated_at.map(|t| t.to_rfc3339()).as_deref(), Some("2024-05-01T10:00:00+00:00"));
+    }
+
+    #[test]
+    fn dry_run_explains_rejected_paths() {
+        let report = OsdrMapping::builtin().dry_run(&json!({"dataset_id": "", "id": "OSD-1", "updated": "soon"}));
+        assert_eq!(report["record"]["dataset_id"], "OSD-1");
+        assert_eq!(report["record"]["updated_at"], Value::Null);
+
+        let fields = report["fields"].as_array().unwrap();
+        let dataset_id = &fields[0];
+        assert_eq!(dataset_id["path"], "/id");
+        assert_eq!(dataset_id["rejected"], json!([{"path": "/dataset_id", "source": "", "reason": "empty string"}]));
+        let updated_at = &fields[3];
+        assert_eq!(updated_at["path"], Value::Null);
+        assert_eq!(updated_at["rejected"][0], json!({"path": "/updated", "source": "soon", "reason": "unrecognised datetime"}));
+        assert_eq!(updated_at["rejected"][1], json!({"path": "/updated_at", "reason": "absent"}));
+    }
+}
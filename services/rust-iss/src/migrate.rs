@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use tracing::{info, warn};
+
+// Миграции только вперёд: файл, уже применённый хоть где-то, не меняется,
+// исправления оформляются новой миграцией. Номер версии - префикс имени файла
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "job_runs",
+        sql: include_str!("../migrations/0002_job_runs.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "iss_position_columns",
+        sql: include_str!("../migrations/0003_iss_position_columns.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "tle_sets",
+        sql: include_str!("../migrations/0004_tle_sets.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "retention",
+        sql: include_str!("../migrations/0005_retention.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "space_cache_dedup",
+        sql: include_str!("../migrations/0006_space_cache_dedup.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "neo",
+        sql: include_str!("../migrations/0007_neo.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "donki",
+        sql: include_str!("../migrations/0008_donki.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "fetch_watermarks",
+        sql: include_str!("../migrations/0009_fetch_watermarks.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "osdr_search",
+        sql: include_str!("../migrations/0010_osdr_search.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "osdr_item_versions",
+        sql: include_str!("../migrations/0011_osdr_item_versions.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "osdr_missing",
+        sql: include_str!("../migrations/0012_osdr_missing.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "osdr_details",
+        sql: include_str!("../migrations/0013_osdr_details.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "iss_hourly_counts",
+        sql: include_str!("../migrations/0014_iss_hourly_counts.sql"),
+    },
+];
+
+// Отдельный от ключей задач (1001..) ключ advisory lock
+const MIGRATION_LOCK_KEY: i64 = 1000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("migration {version} ({name}) was changed after it had been applied")]
+    ChecksumMismatch { version: i64, name: &'static str },
+
+    #[error("{0} migration(s) pending, run `rust_iss --migrate-only` first")]
+    Pending(usize),
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.sql.as_bytes()))
+    }
+}
+
+// Применяет недостающие миграции, каждую в своей транзакции.
+// Несколько реплик ждут друг друга на advisory lock, повторно ничего не применяется
+pub async fn run(pool: &PgPool) -> Result<usize, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = apply_pending(&mut conn).await;
+
+    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await;
+    result
+}
+
+// Для запуска без миграций: схема должна быть уже актуальной
+pub async fn ensure_applied(pool: &PgPool) -> Result<(), MigrateError> {
+    match pending(pool).await?.len() {
+        0 => Ok(()),
+        count => Err(MigrateError::Pending(count)),
+    }
+}
+
+pub async fn pending(pool: &PgPool) -> Result<Vec<&'static Migration>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    let applied = applied_checksums(&mut conn).await?;
+    verify(&applied)?;
+    Ok(MIGRATIONS.iter().filter(|m| !applied.contains_key(&m.version)).collect())
+}
+
+async fn apply_pending(conn: &mut sqlx::PgConnection) -> Result<usize, MigrateError> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations(
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            execution_ms BIGINT NOT NULL
+        )"
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let applied = applied_checksums(conn).await?;
+    verify(&applied)?;
+
+    let mut count = 0;
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains_key(&m.version)) {
+        let started = Instant::now();
+        let mut tx = sqlx::Connection::begin(&mut *conn).await?;
+
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query(
+            "INSERT INTO schema_migrations(version, name, checksum, execution_ms)
+             VALUES ($1, $2, $3, $4)"
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(migration.checksum())
+        .bind(started.elapsed().as_millis() as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        info!(
+            "applied migration {} ({}) in {:?}",
+            migration.version,
+            migration.name,
+            started.elapsed()
+        );
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+async fn applied_checksums(conn: &mut sqlx::PgConnection) -> Result<HashMap<i64, String>, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('schema_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+    if !exists {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query("SELECT version, checksum FROM schema_migrations")
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("version"), row.get("checksum")))
+        .collect())
+}
+
+fn verify(applied: &HashMap<i64, String>) -> Result<(), MigrateError> {
+    for migration in MIGRATIONS {
+        if let Some(checksum) = applied.get(&migration.version) {
+            if *checksum != migration.checksum() {
+                return Err(MigrateError::ChecksumMismatch {
+                    version: migration.version,
+                    name: migration.name,
+                });
+            }
+        }
+    }
+
+    // База новее бинарника (например, при откате релиза) - работаем, но предупреждаем
+    let latest = MIGRATIONS.iter().map(|m| m.version).max().unwrap_or(0);
+    if let Some(newer) = applied.keys().filter(|v| **v > latest).max() {
+        warn!("database schema is at version {}, this build knows up to {}", newer, latest);
+    }
+    Ok(())
+}
@@ -0,0 +1,1102 @@
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Duration, SubsecRound, TimeZone, Utc};
+
+// Орбитальная механика: разбор TLE, пропагатор SGP4 (околоземный вариант,
+// Vallado "Revisiting Spacetrack Report #3") и перевод TEME -> широта/долгота.
+// Глубококосмическая часть (SDP4) не реализована: для МКС она не нужна.
+
+const TWO_PI: f64 = 2.0 * PI;
+const MINUTES_PER_DAY: f64 = 1440.0;
+
+// WGS-72, как в исходной модели SGP4
+const MU: f64 = 398600.8;
+const RADIUS_EARTH_KM: f64 = 6378.135;
+const J2: f64 = 0.001082616;
+const J3: f64 = -0.00000253881;
+const J4: f64 = -0.00000165597;
+const J3OJ2: f64 = J3 / J2;
+const X2O3: f64 = 2.0 / 3.0;
+
+// WGS-84 для перевода в геодезические координаты
+const WGS84_A: f64 = 6378.137;
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+const ASTRONOMICAL_UNIT_KM: f64 = 149597870.7;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrbitError {
+    #[error("invalid TLE: {0}")]
+    InvalidTle(String),
+
+    #[error("deep-space orbits (period >= 225 min) are not supported")]
+    DeepSpace,
+
+    #[error("propagation failed: {0}")]
+    Propagation(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct Tle {
+    pub name: Option<String>,
+    pub norad_id: i32,
+    pub line1: String,
+    pub line2: String,
+    pub epoch: DateTime<Utc>,
+    pub bstar: f64,
+    pub inclination_deg: f64,
+    pub raan_deg: f64,
+    pub eccentricity: f64,
+    pub arg_perigee_deg: f64,
+    pub mean_anomaly_deg: f64,
+    pub mean_motion_rev_day: f64,
+}
+
+impl Tle {
+    // Текст из 2 или 3 строк (с именем спутника или без)
+    pub fn parse(text: &str) -> Result<Self, OrbitError> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|l| l.trim_end())
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        let pos = lines
+            .iter()
+            .position(|l| l.starts_with("1 "))
+            .ok_or_else(|| OrbitError::InvalidTle("line 1 not found".to_string()))?;
+        let line1 = lines[pos];
+        let line2 = lines
+            .get(pos + 1)
+            .filter(|l| l.starts_with("2 "))
+            .ok_or_else(|| OrbitError::InvalidTle("line 2 not found".to_string()))?;
+        let name = pos
+            .checked_sub(1)
+            .map(|i| lines[i].trim_start_matches("0 ").trim().to_string());
+
+        Self::from_lines(name, line1, line2)
+    }
+
+    pub fn from_lines(name: Option<String>, line1: &str, line2: &str) -> Result<Self, OrbitError> {
+        for (n, line) in [(1, line1), (2, line2)] {
+            if line.len() < 69 || !line.is_ascii() {
+                return Err(OrbitError::InvalidTle(format!("line {} is too short", n)));
+            }
+            if checksum(&line[..68]) != line[68..69].parse::<u32>().ok() {
+                return Err(OrbitError::InvalidTle(format!("line {} checksum mismatch", n)));
+            }
+        }
+
+        let norad_id = field(line1, 2, 7)?.parse::<i32>().map_err(|_| bad("catalog number"))?;
+        if field(line2, 2, 7)?.parse::<i32>().ok() != Some(norad_id) {
+            return Err(OrbitError::InvalidTle("catalog numbers differ".to_string()));
+        }
+
+        let year = field(line1, 18, 20)?.parse::<i32>().map_err(|_| bad("epoch year"))?;
+        let day = field(line1, 20, 32)?.parse::<f64>().map_err(|_| bad("epoch day"))?;
+        let year = if year < 57 { 2000 + year } else { 1900 + year };
+        let epoch = Utc
+            .with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+            .single()
+            .ok_or_else(|| bad("epoch"))?
+            + Duration::microseconds(((day - 1.0) * 86400.0e6).round() as i64);
+
+        Ok(Self {
+            name: name.filter(|n| !n.is_empty()),
+            norad_id,
+            line1: line1[..69].to_string(),
+            line2: line2[..69].to_string(),
+            epoch,
+            bstar: implied_decimal(field(line1, 53, 61)?).ok_or_else(|| bad("bstar"))?,
+            inclination_deg: number(line2, 8, 16, "inclination")?,
+            raan_deg: number(line2, 17, 25, "raan")?,
+            eccentricity: format!("0.{}", field(line2, 26, 33)?.trim())
+                .parse::<f64>()
+                .map_err(|_| bad("eccentricity"))?,
+            arg_perigee_deg: number(line2, 34, 42, "argument of perigee")?,
+            mean_anomaly_deg: number(line2, 43, 51, "mean anomaly")?,
+            mean_motion_rev_day: number(line2, 52, 63, "mean motion")?,
+        })
+    }
+}
+
+fn bad(what: &str) -> OrbitError {
+    OrbitError::InvalidTle(format!("cannot parse {}", what))
+}
+
+fn field(line: &str, from: usize, to: usize) -> Result<&str, OrbitError> {
+    line.get(from..to)
+        .ok_or_else(|| OrbitError::InvalidTle("line is too short".to_string()))
+}
+
+fn number(line: &str, from: usize, to: usize, what: &str) -> Result<f64, OrbitError> {
+    field(line, from, to)?.trim().parse::<f64>().map_err(|_| bad(what))
+}
+
+// Формат " 12345-4" = 0.12345e-4
+fn implied_decimal(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some(0.0);
+    }
+    let (sign, s) = match s.as_bytes()[0] {
+        b'-' => (-1.0, &s[1..]),
+        b'+' => (1.0, &s[1..]),
+        _ => (1.0, s),
+    };
+    let split = s.rfind(['-', '+'])?;
+    let mantissa = format!("0.{}", s[..split].trim()).parse::<f64>().ok()?;
+    let exponent = s[split..].parse::<i32>().ok()?;
+    Some(sign * mantissa * 10f64.powi(exponent))
+}
+
+fn checksum(s: &str) -> Option<u32> {
+    Some(
+        s.chars()
+            .map(|c| match c {
+                '-' => 1,
+                c => c.to_digit(10).unwrap_or(0),
+            })
+            .sum::<u32>()
+            % 10,
+    )
+}
+
+// Положение (км) и скорость (км/с) в системе TEME
+#[derive(Debug, Clone, Copy)]
+pub struct StateVector {
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude_km: f64,
+}
+
+// Предвычисленные коэффициенты SGP4 (sgp4init)
+#[derive(Debug, Clone)]
+pub struct Sgp4 {
+    epoch: DateTime<Utc>,
+    isimp: bool,
+    ecco: f64,
+    inclo: f64,
+    nodeo: f64,
+    argpo: f64,
+    mo: f64,
+    no_unkozai: f64,
+    bstar: f64,
+    eta: f64,
+    cc1: f64,
+    cc4: f64,
+    cc5: f64,
+    d2: f64,
+    d3: f64,
+    d4: f64,
+    delmo: f64,
+    sinmao: f64,
+    mdot: f64,
+    argpdot: f64,
+    nodedot: f64,
+    nodecf: f64,
+    omgcof: f64,
+    xmcof: f64,
+    t2cof: f64,
+    t3cof: f64,
+    t4cof: f64,
+    t5cof: f64,
+    xlcof: f64,
+    aycof: f64,
+    con41: f64,
+    x1mth2: f64,
+    x7thm1: f64,
+}
+
+impl Sgp4 {
+    pub fn new(tle: &Tle) -> Result<Self, OrbitError> {
+        let xke = 60.0 / (RADIUS_EARTH_KM.powi(3) / MU).sqrt();
+
+        let ecco = tle.eccentricity;
+        let inclo = tle.inclination_deg.to_radians();
+        let nodeo = tle.raan_deg.to_radians();
+        let argpo = tle.arg_perigee_deg.to_radians();
+        let mo = tle.mean_anomaly_deg.to_radians();
+        let no_kozai = tle.mean_motion_rev_day * TWO_PI / MINUTES_PER_DAY;
+        let bstar = tle.bstar;
+
+        if no_kozai <= 0.0 || !(0.0..1.0).contains(&ecco) {
+            return Err(OrbitError::InvalidTle("mean motion or eccentricity out of range".to_string()));
+        }
+
+        // initl: восстановление "некозаевского" среднего движения
+        let eccsq = ecco * ecco;
+        let omeosq = 1.0 - eccsq;
+        let rteosq = omeosq.sqrt();
+        let cosio = inclo.cos();
+        let cosio2 = cosio * cosio;
+
+        let ak = (xke / no_kozai).powf(X2O3);
+        let d1 = 0.75 * J2 * (3.0 * cosio2 - 1.0) / (rteosq * omeosq);
+        let mut del = d1 / (ak * ak);
+        let adel = ak * (1.0 - del * del - del * (1.0 / 3.0 + 134.0 * del * del / 81.0));
+        del = d1 / (adel * adel);
+        let no_unkozai = no_kozai / (1.0 + del);
+
+        if TWO_PI / no_unkozai >= 225.0 {
+            return Err(OrbitError::DeepSpace);
+        }
+
+        let ao = (xke / no_unkozai).powf(X2O3);
+        let sinio = inclo.sin();
+        let po = ao * omeosq;
+        let con42 = 1.0 - 5.0 * cosio2;
+        let con41 = -con42 - cosio2 - cosio2;
+        let posq = po * po;
+        let rp = ao * (1.0 - ecco);
+
+        // sgp4init
+        let ss = 78.0 / RADIUS_EARTH_KM + 1.0;
+        let qzms2t = ((120.0 - 78.0) / RADIUS_EARTH_KM).powi(4);
+
+        let isimp = rp < 220.0 / RADIUS_EARTH_KM + 1.0;
+        let mut sfour = ss;
+        let mut qzms24 = qzms2t;
+        let perige = (rp - 1.0) * RADIUS_EARTH_KM;
+        if perige < 156.0 {
+            sfour = if perige < 98.0 { 20.0 } else { perige - 78.0 };
+            qzms24 = ((120.0 - sfour) / RADIUS_EARTH_KM).powi(4);
+            sfour = sfour / RADIUS_EARTH_KM + 1.0;
+        }
+
+        let pinvsq = 1.0 / posq;
+        let tsi = 1.0 / (ao - sfour);
+        let eta = ao * ecco * tsi;
+        let etasq = eta * eta;
+        let eeta = ecco * eta;
+        let psisq = (1.0 - etasq).abs();
+        let coef = qzms24 * tsi.powi(4);
+        let coef1 = coef / psisq.powf(3.5);
+        let cc2 = coef1
+            * no_unkozai
+            * (ao * (1.0 + 1.5 * etasq + eeta * (4.0 + etasq))
+                + 0.375 * J2 * tsi / psisq * con41 * (8.0 + 3.0 * etasq * (8.0 + etasq)));
+        let cc1 = bstar * cc2;
+        let cc3 = if ecco > 1.0e-4 {
+            -2.0 * coef * tsi * J3OJ2 * no_unkozai * sinio / ecco
+        } else {
+            0.0
+        };
+        let x1mth2 = 1.0 - cosio2;
+        let cc4 = 2.0
+            * no_unkozai
+            * coef1
+            * ao
+            * omeosq
+            * (eta * (2.0 + 0.5 * etasq) + ecco * (0.5 + 2.0 * etasq)
+                - J2 * tsi / (ao * psisq)
+                    * (-3.0 * con41 * (1.0 - 2.0 * eeta + etasq * (1.5 - 0.5 * eeta))
+                        + 0.75 * x1mth2 * (2.0 * etasq - eeta * (1.0 + etasq)) * (2.0 * argpo).cos()));
+        let cc5 = 2.0 * coef1 * ao * omeosq * (1.0 + 2.75 * (etasq + eeta) + eeta * etasq);
+
+        let cosio4 = cosio2 * cosio2;
+        let temp1 = 1.5 * J2 * pinvsq * no_unkozai;
+        let temp2 = 0.5 * temp1 * J2 * pinvsq;
+        let temp3 = -0.46875 * J4 * pinvsq * pinvsq * no_unkozai;
+        let mdot = no_unkozai
+            + 0.5 * temp1 * rteosq * con41
+            + 0.0625 * temp2 * rteosq * (13.0 - 78.0 * cosio2 + 137.0 * cosio4);
+        let argpdot = -0.5 * temp1 * con42
+            + 0.0625 * temp2 * (7.0 - 114.0 * cosio2 + 395.0 * cosio4)
+            + temp3 * (3.0 - 36.0 * cosio2 + 49.0 * cosio4);
+        let xhdot1 = -temp1 * cosio;
+        let nodedot = xhdot1 + (0.5 * temp2 * (4.0 - 19.0 * cosio2) + 2.0 * temp3 * (3.0 - 7.0 * cosio2)) * cosio;
+
+        let omgcof = bstar * cc3 * argpo.cos();
+        let xmcof = if ecco > 1.0e-4 {
+            -X2O3 * coef * bstar / eeta
+        } else {
+            0.0
+        };
+        let nodecf = 3.5 * omeosq * xhdot1 * cc1;
+        let t2cof = 1.5 * cc1;
+        let xlcof = if (cosio + 1.0).abs() > 1.5e-12 {
+            -0.25 * J3OJ2 * sinio * (3.0 + 5.0 * cosio) / (1.0 + cosio)
+        } else {
+            -0.25 * J3OJ2 * sinio * (3.0 + 5.0 * cosio) / 1.5e-12
+        };
+        let aycof = -0.5 * J3OJ2 * sinio;
+        let delmo = (1.0 + eta * mo.cos()).powi(3);
+        let sinmao = mo.sin();
+        let x7thm1 = 7.0 * cosio2 - 1.0;
+
+        let (mut d2, mut d3, mut d4) = (0.0, 0.0, 0.0);
+        let (mut t3cof, mut t4cof, mut t5cof) = (0.0, 0.0, 0.0);
+        if !isimp {
+            let cc1sq = cc1 * cc1;
+            d2 = 4.0 * ao * tsi * cc1sq;
+            let temp = d2 * tsi * cc1 / 3.0;
+            d3 = (17.0 * ao + sfour) * temp;
+            d4 = 0.5 * temp * ao * tsi * (221.0 * ao + 31.0 * sfour) * cc1;
+            t3cof = d2 + 2.0 * cc1sq;
+            t4cof = 0.25 * (3.0 * d3 + cc1 * (12.0 * d2 + 10.0 * cc1sq));
+            t5cof = 0.2 * (3.0 * d4 + 12.0 * cc1 * d3 + 6.0 * d2 * d2 + 15.0 * cc1sq * (2.0 * d2 + cc1sq));
+        }
+
+        Ok(Self {
+            epoch: tle.epoch,
+            isimp,
+            ecco,
+            inclo,
+            nodeo,
+            argpo,
+            mo,
+            no_unkozai,
+            bstar,
+            eta,
+            cc1,
+            cc4,
+            cc5,
+            d2,
+            d3,
+            d4,
+            delmo,
+            sinmao,
+            mdot,
+            argpdot,
+            nodedot,
+            nodecf,
+            omgcof,
+            xmcof,
+            t2cof,
+            t3cof,
+            t4cof,
+            t5cof,
+            xlcof,
+            aycof,
+            con41,
+            x1mth2,
+            x7thm1,
+        })
+    }
+
+    pub fn propagate_at(&self, at: DateTime<Utc>) -> Result<StateVector, OrbitError> {
+        let minutes = (at - self.epoch).num_microseconds().unwrap_or(i64::MAX) as f64 / 60.0e6;
+        self.propagate(minutes)
+    }
+
+    // tsince - минуты от эпохи TLE
+    pub fn propagate(&self, tsince: f64) -> Result<StateVector, OrbitError> {
+        let xke = 60.0 / (RADIUS_EARTH_KM.powi(3) / MU).sqrt();
+        let t = tsince;
+
+        // Вековые эффекты гравитации и сопротивления атмосферы
+        let xmdf = self.mo + self.mdot * t;
+        let argpdf = self.argpo + self.argpdot * t;
+        let nodedf = self.nodeo + self.nodedot * t;
+        let mut argpm = argpdf;
+        let mut mm = xmdf;
+        let t2 = t * t;
+        let mut nodem = nodedf + self.nodecf * t2;
+        let mut tempa = 1.0 - self.cc1 * t;
+        let mut tempe = self.bstar * self.cc4 * t;
+        let mut templ = self.t2cof * t2;
+
+        if !self.isimp {
+            let delomg = self.omgcof * t;
+            let delm = self.xmcof * ((1.0 + self.eta * xmdf.cos()).powi(3) - self.delmo);
+            let temp = delomg + delm;
+            mm = xmdf + temp;
+            argpm = argpdf - temp;
+            let t3 = t2 * t;
+            let t4 = t3 * t;
+            tempa = tempa - self.d2 * t2 - self.d3 * t3 - self.d4 * t4;
+            tempe += self.bstar * self.cc5 * (mm.sin() - self.sinmao);
+            templ += self.t3cof * t3 + t4 * (self.t4cof + t * self.t5cof);
+        }
+
+        let am = (xke / self.no_unkozai).powf(X2O3) * tempa * tempa;
+        let nm = xke / am.powf(1.5);
+        let mut em = self.ecco - tempe;
+        if !(-0.001..1.0).contains(&em) {
+            return Err(OrbitError::Propagation("eccentricity out of range"));
+        }
+        if em < 1.0e-6 {
+            em = 1.0e-6;
+        }
+        mm += self.no_unkozai * templ;
+        let xlm = mm + argpm + nodem;
+
+        nodem %= TWO_PI;
+        argpm %= TWO_PI;
+        let xlm = xlm % TWO_PI;
+        let mp = (xlm - argpm - nodem) % TWO_PI;
+
+        let ep = em;
+        let xincp = self.inclo;
+        let sinip = xincp.sin();
+        let cosip = xincp.cos();
+
+        // Долгопериодические возмущения
+        let axnl = ep * argpm.cos();
+        let temp = 1.0 / (am * (1.0 - ep * ep));
+        let aynl = ep * argpm.sin() + temp * self.aycof;
+        let xl = mp + argpm + nodem + temp * self.xlcof * axnl;
+
+        // Уравнение Кеплера
+        let u = (xl - nodem) % TWO_PI;
+        let mut eo1 = u;
+        let mut tem5: f64 = 9999.9;
+        let mut ktr = 1;
+        let (mut sineo1, mut coseo1) = (0.0, 0.0);
+        while tem5.abs() >= 1.0e-12 && ktr <= 10 {
+            sineo1 = eo1.sin();
+            coseo1 = eo1.cos();
+            tem5 = 1.0 - coseo1 * axnl - sineo1 * aynl;
+            tem5 = (u - aynl * coseo1 + axnl * sineo1 - eo1) / tem5;
+            if tem5.abs() >= 0.95 {
+                tem5 = 0.95f64.copysign(tem5);
+            }
+            eo1 += tem5;
+            ktr += 1;
+        }
+
+        // Короткопериодические возмущения
+        let ecose = axnl * coseo1 + aynl * sineo1;
+        let esine = axnl * sineo1 - aynl * coseo1;
+        let el2 = axnl * axnl + aynl * aynl;
+        let pl = am * (1.0 - el2);
+        if pl < 0.0 {
+            return Err(OrbitError::Propagation("semi-latus rectum is negative"));
+        }
+
+        let rl = am * (1.0 - ecose);
+        let rdotl = am.sqrt() * esine / rl;
+        let rvdotl = pl.sqrt() / rl;
+        let betal = (1.0 - el2).sqrt();
+        let temp = esine / (1.0 + betal);
+        let sinu = am / rl * (sineo1 - aynl - axnl * temp);
+        let cosu = am / rl * (coseo1 - axnl + aynl * temp);
+        let mut su = sinu.atan2(cosu);
+        let sin2u = (cosu + cosu) * sinu;
+        let cos2u = 1.0 - 2.0 * sinu * sinu;
+        let temp = 1.0 / pl;
+        let temp1 = 0.5 * J2 * temp;
+        let temp2 = temp1 * temp;
+
+        let mrt = rl * (1.0 - 1.5 * temp2 * betal * self.con41) + 0.5 * temp1 * self.x1mth2 * cos2u;
+        su -= 0.25 * temp2 * self.x7thm1 * sin2u;
+        let xnode = nodem + 1.5 * temp2 * cosip * sin2u;
+        let xinc = xincp + 1.5 * temp2 * cosip * sinip * cos2u;
+        let mvt = rdotl - nm * temp1 * self.x1mth2 * sin2u / xke;
+        let rvdot = rvdotl + nm * temp1 * (self.x1mth2 * cos2u + 1.5 * self.con41) / xke;
+
+        if mrt < 1.0 {
+            return Err(OrbitError::Propagation("satellite has decayed"));
+        }
+
+        let (sinsu, cossu) = su.sin_cos();
+        let (snod, cnod) = xnode.sin_cos();
+        let (sini, cosi) = xinc.sin_cos();
+        let xmx = -snod * cosi;
+        let xmy = cnod * cosi;
+        let ux = xmx * sinsu + cnod * cossu;
+        let uy = xmy * sinsu + snod * cossu;
+        let uz = sini * sinsu;
+        let vx = xmx * cossu - cnod * sinsu;
+        let vy = xmy * cossu - snod * sinsu;
+        let vz = sini * cossu;
+
+        let mr = mrt * RADIUS_EARTH_KM;
+        let vkmpersec = RADIUS_EARTH_KM * xke / 60.0;
+
+        Ok(StateVector {
+            position: [mr * ux, mr * uy, mr * uz],
+            velocity: [
+                (mvt * ux + rvdot * vx) * vkmpersec,
+                (mvt * uy + rvdot * vy) * vkmpersec,
+                (mvt * uz + rvdot * vz) * vkmpersec,
+            ],
+        })
+    }
+}
+
+impl Sgp4 {
+    // Подспутниковая точка и скорость (км/ч) на момент времени
+    pub fn subpoint_at(&self, at: DateTime<Utc>) -> Result<(Geodetic, f64), OrbitError> {
+        let state = self.propagate_at(at)?;
+        let geodetic = ecef_to_geodetic(teme_to_ecef(state.position, at));
+        Ok((geodetic, norm(state.velocity) * 3600.0))
+    }
+
+    pub fn look_angles(&self, observer: &Observer, at: DateTime<Utc>) -> Result<LookAngles, OrbitError> {
+        let state = self.propagate_at(at)?;
+        Ok(observer.look_at(teme_to_ecef(state.position, at)))
+    }
+
+    // Пролёты над наблюдателем с максимальной высотой не ниже min_elevation.
+    // Учитываются только пролёты, начавшиеся внутри интервала
+    pub fn passes(
+        &self,
+        observer: &Observer,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        min_elevation: f64,
+    ) -> Result<Vec<Pass>, OrbitError> {
+        let elevation = |at| self.look_angles(observer, at).map(|l| l.elevation);
+        let step = Duration::seconds(PASS_SCAN_STEP_SEC);
+
+        let mut passes = Vec::new();
+        let mut at = from;
+        let mut above = elevation(at)? > 0.0;
+        while at < to {
+            let next = at + step;
+            let next_above = elevation(next)? > 0.0;
+
+            if !above && next_above {
+                let rise = find_crossing(at, next, &elevation)?;
+                // Ищем заход, не ограничиваясь концом интервала
+                let mut set_from = next;
+                while elevation(set_from + step)? > 0.0 {
+                    set_from += step;
+                }
+                let set = find_crossing(set_from, set_from + step, &elevation)?;
+                let culmination = find_culmination(rise, set, &elevation)?;
+
+                let max_elevation = elevation(culmination)?;
+                if max_elevation >= min_elevation {
+                    passes.push(Pass {
+                        rise: self.pass_point(observer, rise)?,
+                        culmination: self.pass_point(observer, culmination)?,
+                        set: self.pass_point(observer, set)?,
+                        visible: self.is_visible(observer, rise, set)?,
+                    });
+                }
+
+                at = set_from + step;
+                above = false;
+                continue;
+            }
+
+            at = next;
+            above = next_above;
+        }
+        Ok(passes)
+    }
+
+    fn pass_point(&self, observer: &Observer, at: DateTime<Utc>) -> Result<PassPoint, OrbitError> {
+        // Моменты найдены с точностью до секунды, дробная часть - шум
+        let at = at.round_subsecs(0);
+        let look = self.look_angles(observer, at)?;
+        Ok(PassPoint {
+            at,
+            azimuth: look.azimuth,
+            elevation: look.elevation,
+            range_km: look.range_km,
+        })
+    }
+
+    // Пролёт виден глазом, если хотя бы в один момент МКС над горизонтом
+    // освещена Солнцем, а у наблюдателя гражданские сумерки уже закончились
+    fn is_visible(&self, observer: &Observer, rise: DateTime<Utc>, set: DateTime<Utc>) -> Result<bool, OrbitError> {
+        let mut at = rise;
+        while at <= set {
+            let state = self.propagate_at(at)?;
+            let sun = sun_position(at);
+            let sun_elevation = observer.look_at(teme_to_ecef(sun, at)).elevation;
+            if sun_elevation < CIVIL_TWILIGHT_DEG && is_sunlit(state.position, sun) {
+                return Ok(true);
+            }
+            at += Duration::seconds(PASS_VISIBILITY_STEP_SEC);
+        }
+        Ok(false)
+    }
+}
+
+const PASS_SCAN_STEP_SEC: i64 = 30;
+const PASS_VISIBILITY_STEP_SEC: i64 = 10;
+const CIVIL_TWILIGHT_DEG: f64 = -6.0;
+
+// Наблюдатель на поверхности Земли (WGS-84)
+#[derive(Debug, Clone, Copy)]
+pub struct Observer {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude_km: f64,
+}
+
+// Азимут от севера по часовой стрелке и высота над горизонтом, градусы
+#[derive(Debug, Clone, Copy)]
+pub struct LookAngles {
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub range_km: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PassPoint {
+    pub at: DateTime<Utc>,
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub range_km: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+    pub rise: PassPoint,
+    pub culmination: PassPoint,
+    pub set: PassPoint,
+    pub visible: bool,
+}
+
+impl Observer {
+    // Топоцентрические координаты точки, заданной в ECEF
+    pub fn look_at(&self, target: [f64; 3]) -> LookAngles {
+        let site = geodetic_to_ecef(self.latitude, self.longitude, self.altitude_km);
+        let rho = [target[0] - site[0], target[1] - site[1], target[2] - site[2]];
+        let (sin_lat, cos_lat) = self.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.to_radians().sin_cos();
+
+        let south = sin_lat * cos_lon * rho[0] + sin_lat * sin_lon * rho[1] - cos_lat * rho[2];
+        let east = -sin_lon * rho[0] + cos_lon * rho[1];
+        let zenith = cos_lat * cos_lon * rho[0] + cos_lat * sin_lon * rho[1] + sin_lat * rho[2];
+
+        let range_km = norm(rho);
+        LookAngles {
+            azimuth: east.atan2(-south).to_degrees().rem_euclid(360.0),
+            elevation: (zenith / range_km).asin().to_degrees(),
+            range_km,
+        }
+    }
+}
+
+// Момент пересечения горизонта между a и b (бисекция до 0.5 с)
+fn find_crossing<F>(a: DateTime<Utc>, b: DateTime<Utc>, elevation: &F) -> Result<DateTime<Utc>, OrbitError>
+where
+    F: Fn(DateTime<Utc>) -> Result<f64, OrbitError>,
+{
+    let rising = elevation(a)? <= 0.0;
+    let (mut lo, mut hi) = (a, b);
+    while hi - lo > Duration::milliseconds(500) {
+        let mid = lo + (hi - lo) / 2;
+        if (elevation(mid)? > 0.0) == rising {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Ok(lo + (hi - lo) / 2)
+}
+
+// Максимум высоты между восходом и заходом (тернарный поиск до 1 с)
+fn find_culmination<F>(a: DateTime<Utc>, b: DateTime<Utc>, elevation: &F) -> Result<DateTime<Utc>, OrbitError>
+where
+    F: Fn(DateTime<Utc>) -> Result<f64, OrbitError>,
+{
+    let (mut lo, mut hi) = (a, b);
+    while hi - lo > Duration::seconds(1) {
+        let third = (hi - lo) / 3;
+        let (m1, m2) = (lo + third, hi - third);
+        if elevation(m1)? < elevation(m2)? {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+    Ok(lo + (hi - lo) / 2)
+}
+
+fn julian_date(at: DateTime<Utc>) -> f64 {
+    at.timestamp_micros() as f64 / 86400.0e6 + 2440587.5
+}
+
+// Среднее звёздное время по Гринвичу (IAU-82), радианы
+pub fn gmst(at: DateTime<Utc>) -> f64 {
+    let tut1 = (julian_date(at) - 2451545.0) / 36525.0;
+    let seconds = -6.2e-6 * tut1.powi(3)
+        + 0.093104 * tut1 * tut1
+        + (876600.0 * 3600.0 + 8640184.812866) * tut1
+        + 67310.54841;
+    (seconds.to_radians() / 240.0).rem_euclid(TWO_PI)
+}
+
+// TEME -> ECEF (без учёта движения полюсов)
+pub fn teme_to_ecef(r: [f64; 3], at: DateTime<Utc>) -> [f64; 3] {
+    let (s, c) = gmst(at).sin_cos();
+    [c * r[0] + s * r[1], -s * r[0] + c * r[1], r[2]]
+}
+
+pub fn ecef_to_geodetic(r: [f64; 3]) -> Geodetic {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let p = (r[0] * r[0] + r[1] * r[1]).sqrt();
+    let longitude = r[1].atan2(r[0]);
+
+    let mut latitude = r[2].atan2(p * (1.0 - e2));
+    let mut n = WGS84_A;
+    for _ in 0..10 {
+        let sin_lat = latitude.sin();
+        n = WGS84_A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        let next = (r[2] + n * e2 * sin_lat).atan2(p);
+        if (next - latitude).abs() < 1.0e-12 {
+            latitude = next;
+            break;
+        }
+        latitude = next;
+    }
+
+    let altitude_km = if latitude.cos().abs() > 1.0e-9 {
+        p / latitude.cos() - n
+    } else {
+        r[2].abs() - n * (1.0 - e2)
+    };
+
+    Geodetic {
+        latitude: latitude.to_degrees(),
+        longitude: longitude.to_degrees(),
+        altitude_km,
+    }
+}
+
+pub fn geodetic_to_ecef(latitude: f64, longitude: f64, altitude_km: f64) -> [f64; 3] {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (sin_lat, cos_lat) = latitude.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = longitude.to_radians().sin_cos();
+    let n = WGS84_A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+    [
+        (n + altitude_km) * cos_lat * cos_lon,
+        (n + altitude_km) * cos_lat * sin_lon,
+        (n * (1.0 - e2) + altitude_km) * sin_lat,
+    ]
+}
+
+// Положение Солнца (км) в инерциальной системе, точность ~0.01°
+// (Astronomical Almanac, low precision formulas); для TEME этого достаточно
+pub fn sun_position(at: DateTime<Utc>) -> [f64; 3] {
+    let n = julian_date(at) - 2451545.0;
+    let mean_longitude = (280.460 + 0.9856474 * n).to_radians();
+    let anomaly = (357.528 + 0.9856003 * n).to_radians();
+    let ecliptic_longitude =
+        mean_longitude + (1.915 * anomaly.sin() + 0.020 * (2.0 * anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+    let distance_km =
+        (1.00014 - 0.01671 * anomaly.cos() - 0.00014 * (2.0 * anomaly).cos()) * ASTRONOMICAL_UNIT_KM;
+
+    let (sin_l, cos_l) = ecliptic_longitude.sin_cos();
+    [
+        distance_km * cos_l,
+        distance_km * obliquity.cos() * sin_l,
+        distance_km * obliquity.sin() * sin_l,
+    ]
+}
+
+// Цилиндрическая модель тени Земли
+pub fn is_sunlit(satellite: [f64; 3], sun: [f64; 3]) -> bool {
+    let sun_norm = norm(sun);
+    let along = (satellite[0] * sun[0] + satellite[1] * sun[1] + satellite[2] * sun[2]) / sun_norm;
+    if along >= 0.0 {
+        return true;
+    }
+    let across = (norm(satellite).powi(2) - along * along).max(0.0).sqrt();
+    across > WGS84_A
+}
+
+pub fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vallado, "Revisiting Spacetrack Report #3" (AIAA 2006-6753), SGP4-VER.TLE / tcppver.out
+    const VANGUARD_1: (&str, &str) = (
+        "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753",
+        "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667",
+    );
+    const SATELLITE_06251: (&str, &str) = (
+        "1 06251U 62025E   06176.82412014  .00008885  00000-0  12808-3 0  3985",
+        "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774",
+    );
+    const SATELLITE_28057: (&str, &str) = (
+        "1 28057U 03049A   06177.78615833  .00000060  00000-0  35940-4 0  1836",
+        "2 28057  98.4283 247.6961 0000884  88.1964 271.9322 14.35478080140550",
+    );
+    const ISS: (&str, &str) = (
+        "1 25544U 98067A   24131.51782528  .00020137  00000+0  35693-3 0  9990",
+        "2 25544  51.6393 162.6215 0003163 154.3186 328.6457 15.50593009453052",
+    );
+
+    fn sgp4(lines: (&str, &str)) -> Sgp4 {
+        Sgp4::new(&Tle::from_lines(None, lines.0, lines.1).unwrap()).unwrap()
+    }
+
+    // Подставляет верную контрольную сумму в строку с изменёнными полями
+    fn with_checksum(line: &str) -> String {
+        let body = &line[..68];
+        format!("{}{}", body, checksum(body).unwrap())
+    }
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3], tolerance: f64) {
+        for i in 0..3 {
+            assert!(
+                (actual[i] - expected[i]).abs() < tolerance,
+                "component {}: {} vs {}",
+                i,
+                actual[i],
+                expected[i]
+            );
+        }
+    }
+
+    #[test]
+    fn sgp4_matches_vallado_vectors() {
+        // (tsince, r км, v км/с)
+        let cases = [
+            (VANGUARD_1, 0.0, [7022.46529266, -1400.08296755, 0.03995155], [1.893841015, 6.405893759, 4.534807250]),
+            (VANGUARD_1, 360.0, [-7154.03120202, -3783.17682504, -3536.19412294], [4.741887409, -4.151817765, -2.093935425]),
+            (VANGUARD_1, 1080.0, [5568.53901181, 4492.06992591, 3863.87641983], [-4.209106476, 5.159719888, 2.744852980]),
+            (SATELLITE_06251, 0.0, [3988.31022699, 5498.96657235, 0.90055879], [-3.290032738, 2.357652820, 6.496623475]),
+            (SATELLITE_28057, 0.0, [-2715.28237486, -6619.26436889, -0.01341443], [-1.008587273, 0.422782003, 7.385272942]),
+        ];
+        for (lines, tsince, r, v) in cases {
+            let state = sgp4(lines).propagate(tsince).unwrap();
+            assert_close(state.position, r, 1.0e-5);
+            assert_close(state.velocity, v, 1.0e-8);
+        }
+    }
+
+    #[test]
+    fn propagate_at_counts_minutes_from_epoch() {
+        let propagator = sgp4(VANGUARD_1);
+        let tle = Tle::from_lines(None, VANGUARD_1.0, VANGUARD_1.1).unwrap();
+        let by_time = propagator.propagate_at(tle.epoch + Duration::minutes(360)).unwrap();
+        let by_minutes = propagator.propagate(360.0).unwrap();
+        assert_close(by_time.position, by_minutes.position, 1.0e-3);
+    }
+
+    #[test]
+    fn parses_tle_fields() {
+        let text = format!("ISS (ZARYA)\n{}\n{}\n", ISS.0, ISS.1);
+        let tle = Tle::parse(&text).unwrap();
+        assert_eq!(tle.name.as_deref(), Some("ISS (ZARYA)"));
+        assert_eq!(tle.norad_id, 25544);
+        assert_eq!(tle.epoch.format("%Y-%m-%d").to_string(), "2024-05-10");
+        assert!((tle.bstar - 0.35693e-3).abs() < 1e-12);
+        assert!((tle.eccentricity - 0.0003163).abs() < 1e-12);
+        assert!((tle.mean_motion_rev_day - 15.50593009).abs() < 1e-9);
+
+        // Без строки с именем
+        let tle = Tle::parse(&format!("{}\n{}", VANGUARD_1.0, VANGUARD_1.1)).unwrap();
+        assert_eq!(tle.name, None);
+        assert_eq!(tle.epoch.format("%Y").to_string(), "2000");
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut line1 = VANGUARD_1.0.to_string();
+        line1.replace_range(68..69, "0");
+        let err = Tle::from_lines(None, &line1, VANGUARD_1.1).unwrap_err();
+        assert!(err.to_string().contains("line 1 checksum mismatch"), "{}", err);
+
+        let mut line2 = VANGUARD_1.1.to_string();
+        line2.replace_range(8..9, "4");
+        let err = Tle::from_lines(None, VANGUARD_1.0, &line2).unwrap_err();
+        assert!(err.to_string().contains("line 2 checksum mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_malformed_tle() {
+        assert!(Tle::parse("").is_err());
+        assert!(Tle::parse(VANGUARD_1.0).is_err());
+        assert!(Tle::from_lines(None, &VANGUARD_1.0[..60], VANGUARD_1.1).is_err());
+        // Номера каталога в строках различаются
+        let line2 = with_checksum(&VANGUARD_1.1.replacen("00005", "00006", 1));
+        assert!(Tle::from_lines(None, VANGUARD_1.0, &line2).is_err());
+    }
+
+    #[test]
+    fn implied_decimal_forms() {
+        let cases = [
+            (" 12345-4", Some(0.12345e-4)),
+            ("-11606-4", Some(-0.11606e-4)),
+            ("+12345+1", Some(0.12345e1)),
+            (" 00000-0", Some(0.0)),
+            (" 00000+0", Some(0.0)),
+            ("        ", Some(0.0)),
+            ("", Some(0.0)),
+            ("abc", None),
+        ];
+        for (input, expected) in cases {
+            let actual = implied_decimal(input);
+            match (actual, expected) {
+                (Some(a), Some(e)) => assert!((a - e).abs() < 1e-15, "{:?}: {} vs {}", input, a, e),
+                _ => assert_eq!(actual, expected, "{:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_deep_space_orbits() {
+        // Период ~1436 мин (геостационар) и ~630 мин (Молния)
+        for mean_motion in [" 1.00270000", " 2.28537848"] {
+            let mut line2 = VANGUARD_1.1.to_string();
+            line2.replace_range(52..63, mean_motion);
+            let tle = Tle::from_lines(None, VANGUARD_1.0, &with_checksum(&line2)).unwrap();
+            assert!(matches!(Sgp4::new(&tle), Err(OrbitError::DeepSpace)));
+        }
+    }
+
+    #[test]
+    fn gmst_matches_vallado_example() {
+        // Vallado, Example 3-5: 1992-08-20 12:14 UT1 -> 152.578787886°
+        let at = Utc.with_ymd_and_hms(1992, 8, 20, 12, 14, 0).unwrap();
+        assert!((gmst(at).to_degrees() - 152.578787886).abs() < 1e-6);
+    }
+
+    #[test]
+    fn geodetic_conversions() {
+        // Vallado, Example 3-3
+        let geodetic = ecef_to_geodetic([6524.834, 6862.875, 6448.296]);
+        assert!((geodetic.latitude - 34.352496).abs() < 1e-5, "{}", geodetic.latitude);
+        assert!((geodetic.longitude - 46.446417).abs() < 1e-5, "{}", geodetic.longitude);
+        assert!((geodetic.altitude_km - 5085.22).abs() < 1e-2, "{}", geodetic.altitude_km);
+
+        for (lat, lon, alt) in [(55.75, 37.62, 0.2), (-33.9, -70.6, 420.0), (89.9, 0.0, 0.0), (0.0, 180.0, 10.0)] {
+            let back = ecef_to_geodetic(geodetic_to_ecef(lat, lon, alt));
+            assert!((back.latitude - lat).abs() < 1e-8);
+            assert!((back.longitude - lon).abs() < 1e-8 || (back.longitude + lon).abs() < 1e-8);
+            assert!((back.altitude_km - alt).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn iss_subpoint_is_plausible() {
+        let propagator = sgp4(ISS);
+        let tle = Tle::from_lines(None, ISS.0, ISS.1).unwrap();
+        for minutes in [0, 45, 90, 720] {
+            let (point, speed) = propagator.subpoint_at(tle.epoch + Duration::minutes(minutes)).unwrap();
+            assert!(point.latitude.abs() <= 51.7, "{}", point.latitude);
+            assert!((400.0..440.0).contains(&point.altitude_km), "{}", point.altitude_km);
+            assert!((27_000.0..28_000.0).contains(&speed), "{}", speed);
+        }
+    }
+
+    #[test]
+    fn sun_position_matches_almanac() {
+        // Vallado, Example 5-1: 2006-04-02 00:00 UTC
+        let at = Utc.with_ymd_and_hms(2006, 4, 2, 0, 0, 0).unwrap();
+        let sun = sun_position(at);
+        let expected = [146_186_212.0, 28_788_976.0, 12_481_064.0];
+        for i in 0..3 {
+            assert!((sun[i] - expected[i]).abs() / norm(expected) < 1e-3, "{:?}", sun);
+        }
+    }
+
+    fn moscow() -> Observer {
+        Observer { latitude: 55.75, longitude: 37.62, altitude_km: 0.15 }
+    }
+
+    fn iss_passes(min_elevation: f64) -> Vec<Pass> {
+        let tle = Tle::from_lines(None, ISS.0, ISS.1).unwrap();
+        sgp4(ISS)
+            .passes(&moscow(), tle.epoch, tle.epoch + Duration::hours(48), min_elevation)
+            .unwrap()
+    }
+
+    #[test]
+    fn passes_are_ordered_and_cross_horizon() {
+        let passes = iss_passes(0.0);
+        assert_eq!(passes.len(), 10);
+        for pass in &passes {
+            assert!(pass.rise.at < pass.culmination.at && pass.culmination.at < pass.set.at, "{:?}", pass);
+            assert!(pass.rise.elevation.abs() < 0.1, "{:?}", pass.rise);
+            assert!(pass.set.elevation.abs() < 0.1, "{:?}", pass.set);
+            assert!(pass.culmination.elevation > 5.0, "{:?}", pass.culmination);
+            let duration = pass.set.at - pass.rise.at;
+            assert!(duration > Duration::minutes(5) && duration < Duration::minutes(15), "{}", duration);
+        }
+        for pair in passes.windows(2) {
+            assert!(pair[0].set.at < pair[1].rise.at);
+        }
+    }
+
+    #[test]
+    fn min_elevation_filters_low_passes() {
+        let all = iss_passes(0.0);
+        let high = iss_passes(20.0);
+        assert_eq!(high.len(), 6);
+        for pass in &high {
+            assert!(pass.culmination.elevation >= 20.0);
+            assert!(all.iter().any(|p| p.rise.at == pass.rise.at && p.set.at == pass.set.at));
+        }
+        let skipped = all.iter().filter(|p| p.culmination.elevation < 20.0).count();
+        assert_eq!(all.len() - skipped, high.len());
+        assert!(iss_passes(90.0).is_empty());
+    }
+
+    #[test]
+    fn visibility_needs_darkness_and_sunlit_station() {
+        let observer = moscow();
+        let propagator = sgp4(ISS);
+        let sun_elevation = |at| observer.look_at(teme_to_ecef(sun_position(at), at)).elevation;
+        let sunlit = |at| is_sunlit(propagator.propagate_at(at).unwrap().position, sun_position(at));
+
+        let passes = iss_passes(0.0);
+        let visible: Vec<bool> = passes.iter().map(|p| p.visible).collect();
+        assert_eq!(visible, [false, true, true, false, false, false, true, true, true, false]);
+
+        // Ночью, но вся МКС в тени Земли
+        let shadowed = &passes[0];
+        assert!(sun_elevation(shadowed.culmination.at) < CIVIL_TWILIGHT_DEG);
+        assert!(!sunlit(shadowed.rise.at) && !sunlit(shadowed.culmination.at) && !sunlit(shadowed.set.at));
+        // МКС освещена, но у наблюдателя гражданские сумерки
+        let twilight = &passes[3];
+        assert!(sunlit(twilight.culmination.at));
+        let sun = sun_elevation(twilight.culmination.at);
+        assert!(sun > CIVIL_TWILIGHT_DEG && sun < 0.0, "{}", sun);
+        // Днём
+        let daylight = &passes[4];
+        assert!(sun_elevation(daylight.rise.at) > 0.0 && sunlit(daylight.culmination.at));
+        // Выходит из тени в тёмном небе
+        let visible = &passes[1];
+        assert!(sun_elevation(visible.set.at) < CIVIL_TWILIGHT_DEG);
+        assert!(!sunlit(visible.rise.at) && sunlit(visible.set.at));
+    }
+
+    #[test]
+    fn crossing_and_culmination_search() {
+        // Парабола с вершиной 10° через 300 с и нулями на 200 и 400 с
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap();
+        let elevation = |at: DateTime<Utc>| {
+            let t = (at - t0).num_milliseconds() as f64 / 1000.0;
+            Ok(10.0 - (t - 300.0).powi(2) / 1000.0)
+        };
+        let seconds = |at: DateTime<Utc>| (at - t0).num_milliseconds() as f64 / 1000.0;
+
+        let rise = find_crossing(t0, t0 + Duration::seconds(250), &elevation).unwrap();
+        assert!((seconds(rise) - 200.0).abs() <= 0.5, "{}", rise);
+        let set = find_crossing(t0 + Duration::seconds(350), t0 + Duration::seconds(600), &elevation).unwrap();
+        assert!((seconds(set) - 400.0).abs() <= 0.5, "{}", set);
+        let culmination = find_culmination(rise, set, &elevation).unwrap();
+        assert!((seconds(culmination) - 300.0).abs() <= 1.0, "{}", culmination);
+    }
+
+    #[test]
+    fn cylindrical_shadow() {
+        let sun = [ASTRONOMICAL_UNIT_KM, 0.0, 0.0];
+        let r = WGS84_A + 420.0;
+        let cases = [
+            ([r, 0.0, 0.0], true),          // под Солнцем
+            ([0.0, r, 0.0], true),          // на терминаторе
+            ([-r, 0.0, 0.0], false),        // за Землёй
+            ([-r, WGS84_A - 10.0, 0.0], false),
+            ([-r, WGS84_A + 10.0, 0.0], true), // за Землёй, но вне цилиндра тени
+            ([-r, 0.0, -(WGS84_A + 10.0)], true),
+        ];
+        for (satellite, lit) in cases {
+            assert_eq!(is_sunlit(satellite, sun), lit, "{:?}", satellite);
+        }
+    }
+}
@@ -1,136 +1,520 @@
-use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use serde_json::Value;
 use sqlx::{PgPool, Row};
 
-use crate::domain::{IssFetchLog, OsdrItem, SpaceCache};
+use crate::domain::{
+    Cme, CmeQuery, FetchWatermark, Flare, FlareQuery, IssFetchLog, IssHourly, IssPosition, IssTrackPoint, JobRun,
+    JobStatus, NeoApproach, NeoObject, NeoQuery, NeoSort, OsdrCursor, OsdrItem, OsdrItemVersion, OsdrRecord, OsdrSearchHit,
+    OsdrSearchQuery, OsdrSort, OsdrSyncReport, RetentionEntry, SourceStats,
+    SpaceCache, TableStats, TleSet,
+};
 
 pub struct IssRepo;
 
+const ISS_COLUMNS: &str = "id, fetched_at, source_url, payload,
+    latitude, longitude, altitude, velocity, visibility, footprint, position_at";
+
 impl IssRepo {
-    pub async fn create(pool: &PgPool, source_url: &str, payload: Value) -> Result<i64, sqlx::Error> {
-        let row = sqlx::query(
-            "INSERT INTO iss_fetch_log (source_url, payload) VALUES ($1, $2) RETURNING id"
-        )
+    pub async fn create(
+        pool: &PgPool,
+        source_url: &str,
+        payload: Value,
+        position: Option<&IssPosition>,
+    ) -> Result<IssFetchLog, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "INSERT INTO iss_fetch_log (source_url, payload,
+                 latitude, longitude, altitude, velocity, visibility, footprint, position_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING {}",
+            ISS_COLUMNS
+        ))
         .bind(source_url)
         .bind(payload)
+        .bind(position.map(|p| p.latitude))
+        .bind(position.map(|p| p.longitude))
+        .bind(position.and_then(|p| p.altitude))
+        .bind(position.and_then(|p| p.velocity))
+        .bind(position.and_then(|p| p.visibility.as_deref()))
+        .bind(position.and_then(|p| p.footprint))
+        .bind(position.and_then(|p| p.timestamp))
         .fetch_one(pool)
         .await?;
         
-        Ok(row.get("id"))
+        Ok(iss_log_from_row(&row))
     }
     
     pub async fn find_last(pool: &PgPool) -> Result<Option<IssFetchLog>, sqlx::Error> {
-        let row_opt = sqlx::query(
-            "SELECT id, fetched_at, source_url, payload
-             FROM iss_fetch_log
-             ORDER BY id DESC LIMIT 1"
-        )
+        let row_opt = sqlx::query(&format!(
+            "SELECT {} FROM iss_fetch_log ORDER BY id DESC LIMIT 1",
+            ISS_COLUMNS
+        ))
         .fetch_optional(pool)
         .await?;
         
-        if let Some(row) = row_opt {
-            Ok(Some(IssFetchLog {
-                id: row.get("id"),
-                fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
-                source_url: row.get("source_url"),
-                payload: row.try_get("payload").unwrap_or(Value::Null),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row_opt.as_ref().map(iss_log_from_row))
+    }
+    
+    // Записи после after_id по возрастанию id (досылка пропущенных событий)
+    pub async fn find_after(pool: &PgPool, after_id: i64, limit: i64) -> Result<Vec<IssFetchLog>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM iss_fetch_log WHERE id > $1 ORDER BY id ASC LIMIT $2",
+            ISS_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(iss_log_from_row).collect())
     }
     
     pub async fn find_last_two(pool: &PgPool) -> Result<Vec<IssFetchLog>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM iss_fetch_log ORDER BY id DESC LIMIT 2",
+            ISS_COLUMNS
+        ))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(iss_log_from_row).collect())
+    }
+    
+    pub async fn find_range(
+        pool: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IssTrackPoint>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT id, fetched_at, source_url, payload
+            "SELECT id, fetched_at, latitude, longitude, altitude, velocity
              FROM iss_fetch_log
-             ORDER BY id DESC LIMIT 2"
+             WHERE fetched_at >= $1 AND fetched_at <= $2
+               AND latitude IS NOT NULL AND longitude IS NOT NULL
+             ORDER BY fetched_at ASC, id ASC"
         )
+        .bind(from)
+        .bind(to)
         .fetch_all(pool)
         .await?;
         
-        Ok(rows.into_iter().map(|row| IssFetchLog {
+        Ok(rows.into_iter().map(|row| IssTrackPoint {
             id: row.get("id"),
-            fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
-            source_url: row.get("source_url"),
-            payload: row.try_get("payload").unwrap_or(Value::Null),
+            at: row.get::<DateTime<Utc>, _>("fetched_at"),
+            latitude: row.get("latitude"),
+            longitude: row.get("longitude"),
+            altitude: row.get("altitude"),
+            velocity: row.get("velocity"),
+        }).collect())
+    }
+    
+    pub async fn find_hourly(
+        pool: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IssHourly>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT hour, samples, altitude_samples, velocity_samples, first_at, last_at,
+                    min_latitude, max_latitude, avg_altitude, min_altitude, max_altitude, avg_velocity
+             FROM iss_position_hourly
+             WHERE hour >= $1 AND hour < $2
+             ORDER BY hour"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| IssHourly {
+            hour: row.get("hour"),
+            samples: row.get("samples"),
+            altitude_samples: row.get("altitude_samples"),
+            velocity_samples: row.get("velocity_samples"),
+            first_at: row.get("first_at"),
+            last_at: row.get("last_at"),
+            min_latitude: row.get("min_latitude"),
+            max_latitude: row.get("max_latitude"),
+            avg_altitude: row.get("avg_altitude"),
+            min_altitude: row.get("min_altitude"),
+            max_altitude: row.get("max_altitude"),
+            avg_velocity: row.get("avg_velocity"),
         }).collect())
     }
 }
 
+fn iss_log_from_row(row: &sqlx::postgres::PgRow) -> IssFetchLog {
+    let latitude: Option<f64> = row.get("latitude");
+    let longitude: Option<f64> = row.get("longitude");
+    
+    IssFetchLog {
+        id: row.get("id"),
+        fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
+        source_url: row.get("source_url"),
+        payload: row.try_get("payload").unwrap_or(Value::Null),
+        position: latitude.zip(longitude).map(|(latitude, longitude)| IssPosition {
+            latitude,
+            longitude,
+            altitude: row.get("altitude"),
+            velocity: row.get("velocity"),
+            visibility: row.get("visibility"),
+            footprint: row.get("footprint"),
+            timestamp: row.get("position_at"),
+        }),
+    }
+}
+
+pub struct TleRepo;
+
+impl TleRepo {
+    // Один и тот же набор элементов (norad_id + epoch) сохраняется один раз
+    pub async fn create(
+        pool: &PgPool,
+        norad_id: i32,
+        name: Option<&str>,
+        line1: &str,
+        line2: &str,
+        epoch: DateTime<Utc>,
+        source: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO tle_sets(norad_id, name, line1, line2, epoch, source)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (norad_id, epoch) DO NOTHING"
+        )
+        .bind(norad_id)
+        .bind(name)
+        .bind(line1)
+        .bind(line2)
+        .bind(epoch)
+        .bind(source)
+        .execute(pool)
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    // Набор с эпохой, ближайшей к заданному моменту
+    pub async fn find_nearest(pool: &PgPool, at: DateTime<Utc>) -> Result<Option<TleSet>, sqlx::Error> {
+        let row_opt = sqlx::query(
+            "SELECT id, norad_id, name, line1, line2, epoch, source, fetched_at
+             FROM tle_sets
+             ORDER BY abs(extract(epoch FROM epoch - $1)) ASC, id DESC
+             LIMIT 1"
+        )
+        .bind(at)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row_opt.map(|row| TleSet {
+            id: row.get("id"),
+            norad_id: row.get("norad_id"),
+            name: row.get("name"),
+            line1: row.get("line1"),
+            line2: row.get("line2"),
+            epoch: row.get::<DateTime<Utc>, _>("epoch"),
+            source: row.get("source"),
+            fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
+        }))
+    }
+}
+
 pub struct OsdrRepo;
 
 impl OsdrRepo {
-    pub async fn upsert(
-        pool: &PgPool,
-        dataset_id: Option<&str>,
-        title: Option<&str>,
-        status: Option<&str>,
-        updated_at: Option<DateTime<Utc>>,
-        raw: Value,
-    ) -> Result<i64, sqlx::Error> {
-        if let Some(ds_id) = dataset_id {
-            // Upsert by dataset_id
-            let row = sqlx::query(
-                "INSERT INTO osdr_items(dataset_id, title, status, updated_at, raw)
-                 VALUES($1, $2, $3, $4, $5)
-                 ON CONFLICT (dataset_id) DO UPDATE
-                 SET title=EXCLUDED.title, 
-                     status=EXCLUDED.status,
-                     updated_at=EXCLUDED.updated_at, 
-                     raw=EXCLUDED.raw
-                 RETURNING id"
+    // Синхронизация целиком в одной транзакции: пачки по batch записей
+    // (многострочный INSERT ... ON CONFLICT с версиями в том же запросе), затем
+    // отметка missing_since у датасетов, которых не было в выдаче. dataset_id
+    // в records должны быть заданы и уникальны
+    pub async fn sync(pool: &PgPool, records: &[OsdrRecord], batch: usize) -> Result<OsdrSyncReport, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let mut report = OsdrSyncReport::default();
+        
+        for chunk in records.chunks(batch.max(1)) {
+            Self::sync_batch(&mut tx, chunk, &mut report).await?;
+        }
+        
+        // Пустая выдача скорее сбой источника, чем удаление всего каталога
+        let seen: Vec<&str> = records.iter().filter_map(|r| r.dataset_id.as_deref()).collect();
+        if !seen.is_empty() {
+            let result = sqlx::query(
+                "UPDATE osdr_items SET missing_since = now()
+                 WHERE dataset_id IS NOT NULL AND missing_since IS NULL
+                   AND NOT (dataset_id = ANY($1))"
             )
-            .bind(ds_id)
-            .bind(title)
-            .bind(status)
-            .bind(updated_at)
-            .bind(raw)
-            .fetch_one(pool)
+            .bind(&seen)
+            .execute(&mut *tx)
             .await?;
-            
-            Ok(row.get("id"))
-        } else {
-            // Insert without dataset_id
-            let row = sqlx::query(
-                "INSERT INTO osdr_items(dataset_id, title, status, updated_at, raw)
-                 VALUES($1, $2, $3, $4, $5)
-                 RETURNING id"
+            report.missing = result.rows_affected() as usize;
+        }
+        
+        tx.commit().await?;
+        Ok(report)
+    }
+    
+    async fn sync_batch(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        records: &[OsdrRecord],
+        report: &mut OsdrSyncReport,
+    ) -> Result<(), sqlx::Error> {
+        let ids: Vec<&str> = records.iter().filter_map(|r| r.dataset_id.as_deref()).collect();
+        let rows = sqlx::query(
+            "SELECT dataset_id, title, status, updated_at, raw FROM osdr_items
+             WHERE dataset_id = ANY($1) FOR UPDATE"
+        )
+        .bind(&ids)
+        .fetch_all(&mut **tx)
+        .await?;
+        let previous: HashMap<String, OsdrRecord> = rows
+            .iter()
+            .map(|row| (row.get("dataset_id"), OsdrRecord {
+                dataset_id: row.get("dataset_id"),
+                title: row.get("title"),
+                status: row.get("status"),
+                updated_at: row.get("updated_at"),
+                raw: row.get("raw"),
+                details_fetched: false,
+            }))
+            .collect();
+        
+        // Записываются только новые и изменившиеся; у неизменных лишь снимается
+        // missing_since и отмечается запрос метаданных. Если raw тот же, а колонки
+        // разошлись (сменился профиль разбора), колонки переписываются без новой версии
+        let mut write = Vec::new();
+        let mut diffs = Vec::new();
+        let mut versioned = Vec::new();
+        let mut unchanged = Vec::new();
+        let mut unchanged_fetched = Vec::new();
+        for record in records {
+            match record.dataset_id.as_deref().and_then(|id| previous.get(id)) {
+                Some(old) if old.raw == record.raw => {
+                    if (&old.title, &old.status, &old.updated_at) == (&record.title, &record.status, &record.updated_at) {
+                        if let Some(id) = record.dataset_id.as_deref() {
+                            unchanged.push(id);
+                            unchanged_fetched.push(record.details_fetched);
+                        }
+                        continue;
+                    }
+                    report.updated += 1;
+                    diffs.push(None);
+                    versioned.push(false);
+                }
+                Some(old) => {
+                    report.updated += 1;
+                    diffs.push(Some(crate::diff::json_diff(&old.raw, &record.raw)));
+                    versioned.push(true);
+                }
+                None => {
+                    report.inserted += 1;
+                    diffs.push(None);
+                    versioned.push(true);
+                }
+            }
+            write.push(record);
+        }
+        report.unchanged += unchanged.len();
+        
+        if !unchanged.is_empty() {
+            sqlx::query(
+                "UPDATE osdr_items o
+                 SET missing_since = NULL,
+                     details_fetched_at = CASE WHEN u.fetched THEN now() ELSE o.details_fetched_at END
+                 FROM unnest($1::text[], $2::bool[]) AS u(dataset_id, fetched)
+                 WHERE o.dataset_id = u.dataset_id AND (o.missing_since IS NOT NULL OR u.fetched)"
             )
-            .bind::<Option<String>>(None)
-            .bind(title)
-            .bind(status)
-            .bind(updated_at)
-            .bind(raw)
-            .fetch_one(pool)
+            .bind(&unchanged)
+            .bind(&unchanged_fetched)
+            .execute(&mut **tx)
             .await?;
-            
-            Ok(row.get("id"))
         }
+        if write.is_empty() {
+            return Ok(());
+        }
+        
+        // Версия - следующая за последней у датасета; diff сопоставляется по dataset_id
+        sqlx::query(
+            "WITH input AS (
+                 SELECT * FROM unnest(
+                     $1::text[], $2::text[], $3::text[], $4::timestamptz[], $5::jsonb[], $6::jsonb[], $7::bool[], $8::bool[]
+                 ) AS t(dataset_id, title, status, updated_at, raw, diff, fetched, versioned)
+             ),
+             written AS (
+                 INSERT INTO osdr_items (dataset_id, title, status, updated_at, raw, details_fetched_at)
+                 SELECT dataset_id, title, status, updated_at, raw, CASE WHEN fetched THEN now() END FROM input
+                 ON CONFLICT (dataset_id) WHERE dataset_id IS NOT NULL DO UPDATE
+                 SET title = EXCLUDED.title,
+                     status = EXCLUDED.status,
+                     updated_at = EXCLUDED.updated_at,
+                     raw = EXCLUDED.raw,
+                     missing_since = NULL,
+                     details_fetched_at = coalesce(EXCLUDED.details_fetched_at, osdr_items.details_fetched_at)
+                 RETURNING id, dataset_id, title, status, updated_at, raw
+             )
+             INSERT INTO osdr_item_versions (item_id, version, title, status, updated_at, raw, diff)
+             SELECT w.id,
+                    coalesce((SELECT max(v.version) FROM osdr_item_versions v WHERE v.item_id = w.id), 0) + 1,
+                    w.title, w.status, w.updated_at, w.raw, i.diff
+             FROM written w
+             JOIN input i ON i.dataset_id = w.dataset_id
+             WHERE i.versioned"
+        )
+        .bind(write.iter().map(|r| r.dataset_id.clone()).collect::<Vec<_>>())
+        .bind(write.iter().map(|r| r.title.clone()).collect::<Vec<_>>())
+        .bind(write.iter().map(|r| r.status.clone()).collect::<Vec<_>>())
+        .bind(write.iter().map(|r| r.updated_at).collect::<Vec<_>>())
+        .bind(write.iter().map(|r| r.raw.clone()).collect::<Vec<_>>())
+        .bind(diffs)
+        .bind(write.iter().map(|r| r.details_fetched).collect::<Vec<_>>())
+        .bind(versioned)
+        .execute(&mut **tx)
+        .await?;
+        
+        Ok(())
     }
     
-    pub async fn list(pool: &PgPool, limit: i64) -> Result<Vec<OsdrItem>, sqlx::Error> {
+    // Сохранённый raw и время запроса метаданных по dataset_id
+    pub async fn find_details(
+        pool: &PgPool,
+        dataset_ids: &[&str],
+    ) -> Result<HashMap<String, (Value, Option<DateTime<Utc>>)>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT id, dataset_id, title, status, updated_at, inserted_at, raw
-             FROM osdr_items
-             ORDER BY inserted_at DESC
-             LIMIT $1"
+            "SELECT dataset_id, raw, details_fetched_at FROM osdr_items WHERE dataset_id = ANY($1)"
+        )
+        .bind(dataset_ids)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("dataset_id"), (row.get("raw"), row.get("details_fetched_at"))))
+            .collect())
+    }
+    
+    pub async fn find_by_dataset_id(pool: &PgPool, dataset_id: &str) -> Result<Option<OsdrItem>, sqlx::Error> {
+        let row = sqlx::query(&format!("SELECT {} FROM osdr_items WHERE dataset_id = $1", OSDR_COLUMNS))
+            .bind(dataset_id)
+            .fetch_optional(pool)
+            .await?;
+        
+        Ok(row.as_ref().map(osdr_item_from_row))
+    }
+    
+    // Количество версий и время последней
+    pub async fn version_stats(pool: &PgPool, item_id: i64) -> Result<(i64, Option<DateTime<Utc>>), sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT count(*) AS versions, max(recorded_at) AS last_changed_at
+             FROM osdr_item_versions WHERE item_id = $1"
         )
+        .bind(item_id)
+        .fetch_one(pool)
+        .await?;
+        
+        Ok((row.get("versions"), row.get("last_changed_at")))
+    }
+    
+    // Версии от новых к старым; before - номер версии, с которой продолжить
+    pub async fn find_versions(
+        pool: &PgPool,
+        item_id: i64,
+        before: Option<i32>,
+        limit: i64,
+        with_raw: bool,
+    ) -> Result<Vec<OsdrItemVersion>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT version, recorded_at, title, status, updated_at, diff,
+                    CASE WHEN $3 THEN raw END AS raw
+             FROM osdr_item_versions
+             WHERE item_id = $1 AND ($2::integer IS NULL OR version < $2)
+             ORDER BY version DESC
+             LIMIT $4"
+        )
+        .bind(item_id)
+        .bind(before)
+        .bind(with_raw)
         .bind(limit)
         .fetch_all(pool)
         .await?;
         
-        Ok(rows.into_iter().map(|row| OsdrItem {
-            id: row.get("id"),
-            dataset_id: row.get("dataset_id"),
+        Ok(rows.iter().map(|row| OsdrItemVersion {
+            version: row.get("version"),
+            recorded_at: row.get("recorded_at"),
             title: row.get("title"),
             status: row.get("status"),
             updated_at: row.get("updated_at"),
-            inserted_at: row.get::<DateTime<Utc>, _>("inserted_at"),
+            diff: row.get("diff"),
             raw: row.get("raw"),
         }).collect())
     }
     
+    pub async fn list(pool: &PgPool, limit: i64) -> Result<Vec<OsdrItem>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM osdr_items ORDER BY inserted_at DESC LIMIT $1",
+            OSDR_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(osdr_item_from_row).collect())
+    }
+    
+    // Поиск по search_vector (websearch-синтаксис: фразы в кавычках, OR, -слово)
+    // с фильтрами и keyset-пагинацией. Фрагменты ts_headline считаются во внешнем
+    // запросе, только для строк страницы
+    pub async fn search(pool: &PgPool, query: &OsdrSearchQuery) -> Result<Vec<OsdrSearchHit>, sqlx::Error> {
+        // Ключ сортировки и тип значения курсора для него
+        let (sort_key, after_key) = match query.sort {
+            OsdrSort::Relevance => ("ts_rank_cd(o.search_vector, q.query)", "$4::real"),
+            OsdrSort::Updated => ("coalesce(o.updated_at, 'epoch'::timestamptz)", "$5::timestamptz"),
+            OsdrSort::Inserted => ("o.inserted_at", "$5::timestamptz"),
+        };
+        let (after_rank, after_at, after_id) = match query.after {
+            Some(OsdrCursor::Rank(rank, id)) => (Some(rank), None, Some(id)),
+            Some(OsdrCursor::Time(at, id)) => (None, Some(at), Some(id)),
+            None => (None, None, None),
+        };
+        
+        let rows = sqlx::query(&format!(
+            "WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query),
+             page AS (
+                 SELECT o.id, o.dataset_id, o.title, o.status, o.updated_at, o.inserted_at,
+                        o.missing_since, o.details_fetched_at, o.raw,
+                        ts_rank_cd(o.search_vector, q.query) AS rank,
+                        {sort_key} AS sort_key
+                 FROM osdr_items o, q
+                 WHERE ($1::text IS NULL OR o.search_vector @@ q.query)
+                   AND ($2::text IS NULL OR lower(o.status) = lower($2))
+                   AND ($3::timestamptz IS NULL OR o.updated_at >= $3)
+                   AND ($6::bigint IS NULL OR ({sort_key}, o.id) < ({after_key}, $6))
+                 ORDER BY {sort_key} DESC, o.id DESC
+                 LIMIT $7
+             )
+             SELECT page.*,
+                    CASE WHEN $1::text IS NULL THEN NULL ELSE ts_headline(
+                        'english',
+                        concat_ws(' ', page.title, page.raw->>'description'),
+                        q.query,
+                        'StartSel=<mark>, StopSel=</mark>, MaxWords=30, MinWords=10, MaxFragments=2'
+                    ) END AS snippet
+             FROM page, q
+             ORDER BY page.sort_key DESC, page.id DESC",
+        ))
+        .bind(query.q.as_deref())
+        .bind(query.status.as_deref())
+        .bind(query.updated_since)
+        .bind(after_rank)
+        .bind(after_at)
+        .bind(after_id)
+        .bind(query.limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| OsdrSearchHit {
+            item: osdr_item_from_row(row),
+            rank: query.q.as_ref().map(|_| row.get("rank")),
+            snippet: row.get("snippet"),
+        }).collect())
+    }
+    
     pub async fn count(pool: &PgPool) -> Result<i64, sqlx::Error> {
         let row = sqlx::query("SELECT count(*) AS c FROM osdr_items")
             .fetch_one(pool)
@@ -140,99 +524,1010 @@ impl OsdrRepo {
     }
 }
 
+const OSDR_COLUMNS: &str =
+    "id, dataset_id, title, status, updated_at, inserted_at, missing_since, details_fetched_at, raw";
+
+fn osdr_item_from_row(row: &sqlx::postgres::PgRow) -> OsdrItem {
+    OsdrItem {
+        id: row.get("id"),
+        dataset_id: row.get("dataset_id"),
+        title: row.get("title"),
+        status: row.get("status"),
+        updated_at: row.get("updated_at"),
+        inserted_at: row.get("inserted_at"),
+        missing_since: row.get("missing_since"),
+        details_fetched_at: row.get("details_fetched_at"),
+        raw: row.get("raw"),
+    }
+}
+
 pub struct CacheRepo;
 
+// Хэш от канонического текста jsonb, как и в миграции 0006
+const PAYLOAD_HASH_SQL: &str = "encode(sha256(convert_to($2::jsonb::text, 'UTF8')), 'hex')";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheWrite {
+    New(i64),
+    // Содержимое совпало с последней строкой источника, у неё обновлён last_seen_at
+    Unchanged(i64),
+}
+
+impl CacheWrite {
+    pub fn id(&self) -> i64 {
+        match self {
+            CacheWrite::New(id) | CacheWrite::Unchanged(id) => *id,
+        }
+    }
+}
+
 impl CacheRepo {
-    pub async fn create(pool: &PgPool, source: &str, payload: Value) -> Result<i64, sqlx::Error> {
-        let row = sqlx::query(
-            "INSERT INTO space_cache(source, payload) VALUES ($1, $2) RETURNING id"
-        )
+    pub async fn create(pool: &PgPool, source: &str, payload: Value) -> Result<CacheWrite, sqlx::Error> {
+        let touched: Option<i64> = sqlx::query_scalar(&format!(
+            "UPDATE space_cache SET last_seen_at = now()
+             WHERE id = (SELECT id FROM space_cache WHERE source = $1 ORDER BY id DESC LIMIT 1)
+               AND payload_hash = {}
+             RETURNING id",
+            PAYLOAD_HASH_SQL
+        ))
         .bind(source)
-        .bind(payload)
+        .bind(&payload)
+        .fetch_optional(pool)
+        .await?;
+        
+        if let Some(id) = touched {
+            return Ok(CacheWrite::Unchanged(id));
+        }
+        
+        let id = sqlx::query_scalar(&format!(
+            "INSERT INTO space_cache(source, payload, payload_hash) VALUES ($1, $2, {}) RETURNING id",
+            PAYLOAD_HASH_SQL
+        ))
+        .bind(source)
+        .bind(&payload)
         .fetch_one(pool)
         .await?;
         
-        Ok(row.get("id"))
+        Ok(CacheWrite::New(id))
     }
     
     pub async fn find_latest(pool: &PgPool, source: &str) -> Result<Option<SpaceCache>, sqlx::Error> {
-        let row_opt = sqlx::query(
-            "SELECT id, source, fetched_at, payload
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM space_cache WHERE source = $1 ORDER BY id DESC LIMIT 1",
+            CACHE_COLUMNS
+        ))
+        .bind(source)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.as_ref().map(space_cache_from_row))
+    }
+    
+    pub async fn find_by_id(pool: &PgPool, source: &str, id: i64) -> Result<Option<SpaceCache>, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM space_cache WHERE source = $1 AND id = $2",
+            CACHE_COLUMNS
+        ))
+        .bind(source)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.as_ref().map(space_cache_from_row))
+    }
+    
+    // Страница истории от новых к старым, keyset по (fetched_at, id):
+    // after - последняя строка предыдущей страницы
+    pub async fn find_page(
+        pool: &PgPool,
+        source: &str,
+        range: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+        after: Option<(DateTime<Utc>, i64)>,
+        limit: i64,
+    ) -> Result<Vec<SpaceCache>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}
              FROM space_cache
              WHERE source = $1
-             ORDER BY id DESC LIMIT 1"
+               AND ($2::TIMESTAMPTZ IS NULL OR fetched_at >= $2)
+               AND ($3::TIMESTAMPTZ IS NULL OR fetched_at < $3)
+               AND ($4::TIMESTAMPTZ IS NULL OR (fetched_at, id) < ($4, $5))
+             ORDER BY fetched_at DESC, id DESC
+             LIMIT $6",
+            CACHE_COLUMNS
+        ))
+        .bind(source)
+        .bind(range.0)
+        .bind(range.1)
+        .bind(after.map(|(at, _)| at))
+        .bind(after.map(|(_, id)| id).unwrap_or(0))
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(space_cache_from_row).collect())
+    }
+}
+
+const CACHE_COLUMNS: &str = "id, source, fetched_at, last_seen_at, payload_hash, payload";
+
+fn space_cache_from_row(row: &sqlx::postgres::PgRow) -> SpaceCache {
+    SpaceCache {
+        id: row.get("id"),
+        source: row.get("source"),
+        fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
+        last_seen_at: row.get("last_seen_at"),
+        payload_hash: row.get("payload_hash"),
+        payload: row.get("payload"),
+    }
+}
+
+pub struct JobRunRepo;
+
+impl JobRunRepo {
+    pub async fn start(pool: &PgPool, job_name: &str, trigger: &str) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query(
+            "INSERT INTO job_runs(job_name, trigger, outcome) VALUES ($1, $2, 'running') RETURNING id"
+        )
+        .bind(job_name)
+        .bind(trigger)
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(row.get("id"))
+    }
+    
+    // Запуски, оставшиеся 'running' после падения или abort прошлого процесса.
+    // Задачи, чей advisory lock сейчас держит другая реплика, не трогаются; если
+    // отмеченный запуск на самом деле жив, finish всё равно перепишет его итог
+    pub async fn interrupt_stale(pool: &PgPool, locks: &[(&str, i64)]) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE job_runs r
+             SET outcome = 'failed', error = 'interrupted', finished_at = now()
+             WHERE r.outcome = 'running'
+               AND NOT EXISTS (
+                   SELECT 1
+                   FROM unnest($1::text[], $2::bigint[]) AS j(job_name, lock_key)
+                   JOIN pg_locks l ON l.locktype = 'advisory' AND l.granted AND l.objsubid = 1
+                       AND l.classid::bigint = (j.lock_key >> 32)
+                       AND l.objid::bigint = (j.lock_key & 4294967295)
+                   WHERE j.job_name = r.job_name
+               )"
         )
+        .bind(locks.iter().map(|(name, _)| *name).collect::<Vec<_>>())
+        .bind(locks.iter().map(|(_, key)| *key).collect::<Vec<_>>())
+        .execute(pool)
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    pub async fn finish(
+        pool: &PgPool,
+        id: i64,
+        outcome: &str,
+        error: Option<&str>,
+        rows: Option<(i64, i64)>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE job_runs
+             SET finished_at = now(), outcome = $2, error = $3, rows_written = $4, rows_unchanged = $5
+             WHERE id = $1"
+        )
+        .bind(id)
+        .bind(outcome)
+        .bind(error)
+        .bind(rows.map(|(written, _)| written))
+        .bind(rows.map(|(_, unchanged)| unchanged))
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    pub async fn find_runs(pool: &PgPool, job_name: &str, limit: i64) -> Result<Vec<JobRun>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, job_name, trigger, started_at, finished_at, outcome, error, rows_written, rows_unchanged
+             FROM job_runs
+             WHERE job_name = $1
+             ORDER BY started_at DESC, id DESC
+             LIMIT $2"
+        )
+        .bind(job_name)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(job_run_from_row).collect())
+    }
+    
+    // Последний запуск каждой задачи + время последнего успеха/ошибки
+    pub async fn find_statuses(pool: &PgPool) -> Result<Vec<JobStatus>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT DISTINCT ON (r.job_name)
+                    r.id, r.job_name, r.trigger, r.started_at, r.finished_at,
+                    r.outcome, r.error, r.rows_written, r.rows_unchanged,
+                    s.last_success_at, s.last_failure_at
+             FROM job_runs r
+             JOIN (
+                 SELECT job_name,
+                        max(finished_at) FILTER (WHERE outcome = 'success') AS last_success_at,
+                        max(finished_at) FILTER (WHERE outcome = 'failed') AS last_failure_at
+                 FROM job_runs
+                 GROUP BY job_name
+             ) s ON s.job_name = r.job_name
+             ORDER BY r.job_name, r.started_at DESC, r.id DESC"
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| JobStatus {
+            name: row.get("job_name"),
+            schedule: None,
+            last_run: Some(job_run_from_row(row)),
+            last_success_at: row.get("last_success_at"),
+            last_failure_at: row.get("last_failure_at"),
+            stale: false,
+        }).collect())
+    }
+}
+
+fn job_run_from_row(row: &sqlx::postgres::PgRow) -> JobRun {
+    JobRun {
+        id: row.get("id"),
+        job_name: row.get("job_name"),
+        trigger: row.get("trigger"),
+        started_at: row.get::<DateTime<Utc>, _>("started_at"),
+        finished_at: row.get("finished_at"),
+        outcome: row.get("outcome"),
+        error: row.get("error"),
+        rows_written: row.get("rows_written"),
+        rows_unchanged: row.get("rows_unchanged"),
+    }
+}
+
+// Таблицы, доступные для выгрузки и очистки из rust_iss-admin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataTable {
+    IssFetchLog,
+    OsdrItems,
+    SpaceCache,
+    JobRuns,
+}
+
+impl DataTable {
+    pub fn name(self) -> &'static str {
+        match self {
+            DataTable::IssFetchLog => "iss_fetch_log",
+            DataTable::OsdrItems => "osdr_items",
+            DataTable::SpaceCache => "space_cache",
+            DataTable::JobRuns => "job_runs",
+        }
+    }
+    
+    // Колонка времени для фильтров from/to и срока хранения
+    fn time_column(self) -> &'static str {
+        match self {
+            DataTable::IssFetchLog | DataTable::SpaceCache => "fetched_at",
+            DataTable::OsdrItems => "inserted_at",
+            DataTable::JobRuns => "started_at",
+        }
+    }
+    
+    // Фильтр по источнику ($param) есть только у space_cache
+    fn source_filter(self, param: usize) -> String {
+        match self {
+            DataTable::SpaceCache => format!("(${0}::TEXT IS NULL OR source = ${0})", param),
+            _ => format!("${}::TEXT IS NULL", param),
+        }
+    }
+}
+
+pub struct MaintenanceRepo;
+
+impl MaintenanceRepo {
+    // Пачка строк в виде JSON-объектов, keyset-пагинация по id
+    pub async fn export_batch(
+        pool: &PgPool,
+        table: DataTable,
+        after_id: i64,
+        range: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+        source: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<(i64, Value)>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT t.id, to_jsonb(t) AS row
+             FROM {table} t
+             WHERE t.id > $1
+               AND ($2::TIMESTAMPTZ IS NULL OR t.{ts} >= $2)
+               AND ($3::TIMESTAMPTZ IS NULL OR t.{ts} < $3)
+               AND {source}
+             ORDER BY t.id
+             LIMIT $5",
+            table = table.name(),
+            ts = table.time_column(),
+            source = table.source_filter(4),
+        ))
+        .bind(after_id)
+        .bind(range.0)
+        .bind(range.1)
         .bind(source)
-        .fetch_optional(pool)
+        .bind(limit)
+        .fetch_all(pool)
         .await?;
         
-        if let Some(row) = row_opt {
-            Ok(Some(SpaceCache {
-                id: row.get("id"),
-                source: row.get("source"),
-                fetched_at: row.get::<DateTime<Utc>, _>("fetched_at"),
-                payload: row.get("payload"),
-            }))
-        } else {
-            Ok(None)
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("row"))).collect())
+    }
+    
+    pub async fn count_older(
+        pool: &PgPool,
+        table: DataTable,
+        cutoff: DateTime<Utc>,
+        source: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT count(*) AS c FROM {table} WHERE {ts} < $1 AND {source}",
+            table = table.name(),
+            ts = table.time_column(),
+            source = table.source_filter(2),
+        ))
+        .bind(cutoff)
+        .bind(source)
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(row.get("c"))
+    }
+    
+    // Удаляет не больше batch строк старше cutoff, чтобы не держать долгих блокировок
+    pub async fn delete_older_batch(
+        pool: &PgPool,
+        table: DataTable,
+        cutoff: DateTime<Utc>,
+        source: Option<&str>,
+        batch: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE id IN (
+                SELECT id FROM {table} WHERE {ts} < $1 AND {source} ORDER BY id LIMIT $2
+             )",
+            table = table.name(),
+            ts = table.time_column(),
+            source = table.source_filter(3),
+        ))
+        .bind(cutoff)
+        .bind(batch)
+        .bind(source)
+        .execute(pool)
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    pub async fn table_stats(pool: &PgPool) -> Result<Vec<TableStats>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT relname AS name, n_live_tup AS rows, pg_total_relation_size(relid) AS bytes
+             FROM pg_stat_user_tables
+             ORDER BY relname"
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| TableStats {
+            table: row.get("name"),
+            rows_estimate: row.get("rows"),
+            total_bytes: row.get("bytes"),
+        }).collect())
+    }
+    
+    pub async fn source_stats(pool: &PgPool) -> Result<Vec<SourceStats>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT source, count(*) AS rows, max(fetched_at) AS latest_at
+             FROM space_cache
+             GROUP BY source
+             ORDER BY source"
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| SourceStats {
+            source: row.get("source"),
+            rows: row.get("rows"),
+            latest_at: row.get("latest_at"),
+        }).collect())
+    }
+    
+    pub async fn schema_version(pool: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT max(version) FROM schema_migrations")
+            .fetch_one(pool)
+            .await
+    }
+}
+
+pub struct RetentionRepo;
+
+impl RetentionRepo {
+    pub async fn oldest_raw_iss(pool: &PgPool) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar("SELECT min(fetched_at) FROM iss_fetch_log")
+            .fetch_one(pool)
+            .await
+    }
+    
+    pub async fn count_raw_iss(
+        pool: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(i64, i64), sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT count(*) AS rows,
+                    count(DISTINCT date_trunc('hour', fetched_at AT TIME ZONE 'UTC')) AS hours
+             FROM iss_fetch_log
+             WHERE fetched_at >= $1 AND fetched_at < $2"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_one(pool)
+        .await?;
+        
+        Ok((row.get("hours"), row.get("rows")))
+    }
+    
+    // Сворачивает сырые строки окна [from, to) в почасовые агрегаты и удаляет их
+    // в одной транзакции. Если час уже есть, агрегаты объединяются
+    pub async fn compact_iss_window(
+        pool: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(u64, u64), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        
+        let aggregated = sqlx::query(
+            "INSERT INTO iss_position_hourly AS h (hour, samples, altitude_samples, velocity_samples,
+                 first_at, last_at, min_latitude, max_latitude, avg_altitude, min_altitude, max_altitude, avg_velocity)
+             SELECT date_trunc('hour', fetched_at AT TIME ZONE 'UTC') AT TIME ZONE 'UTC',
+                    count(*), count(altitude), count(velocity), min(fetched_at), max(fetched_at),
+                    min(latitude), max(latitude),
+                    avg(altitude), min(altitude), max(altitude), avg(velocity)
+             FROM iss_fetch_log
+             WHERE fetched_at >= $1 AND fetched_at < $2
+             GROUP BY 1
+             ON CONFLICT (hour) DO UPDATE SET
+                 samples = h.samples + EXCLUDED.samples,
+                 altitude_samples = h.altitude_samples + EXCLUDED.altitude_samples,
+                 velocity_samples = h.velocity_samples + EXCLUDED.velocity_samples,
+                 first_at = LEAST(h.first_at, EXCLUDED.first_at),
+                 last_at = GREATEST(h.last_at, EXCLUDED.last_at),
+                 min_latitude = LEAST(h.min_latitude, EXCLUDED.min_latitude),
+                 max_latitude = GREATEST(h.max_latitude, EXCLUDED.max_latitude),
+                 avg_altitude = COALESCE((h.avg_altitude * h.altitude_samples + EXCLUDED.avg_altitude * EXCLUDED.altitude_samples)
+                                         / (h.altitude_samples + EXCLUDED.altitude_samples), h.avg_altitude, EXCLUDED.avg_altitude),
+                 min_altitude = LEAST(h.min_altitude, EXCLUDED.min_altitude),
+                 max_altitude = GREATEST(h.max_altitude, EXCLUDED.max_altitude),
+                 avg_velocity = COALESCE((h.avg_velocity * h.velocity_samples + EXCLUDED.avg_velocity * EXCLUDED.velocity_samples)
+                                         / (h.velocity_samples + EXCLUDED.velocity_samples), h.avg_velocity, EXCLUDED.avg_velocity)"
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        
+        let deleted = sqlx::query("DELETE FROM iss_fetch_log WHERE fetched_at >= $1 AND fetched_at < $2")
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        
+        tx.commit().await?;
+        Ok((aggregated, deleted))
+    }
+    
+    pub async fn count_hourly_older(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT count(*) FROM iss_position_hourly WHERE hour < $1")
+            .bind(cutoff)
+            .fetch_one(pool)
+            .await
+    }
+    
+    pub async fn delete_hourly_older_batch(
+        pool: &PgPool,
+        cutoff: DateTime<Utc>,
+        batch: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM iss_position_hourly WHERE hour IN (
+                SELECT hour FROM iss_position_hourly WHERE hour < $1 ORDER BY hour LIMIT $2
+             )"
+        )
+        .bind(cutoff)
+        .bind(batch)
+        .execute(pool)
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    pub async fn space_sources(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT DISTINCT source FROM space_cache ORDER BY source")
+            .fetch_all(pool)
+            .await
+    }
+    
+    pub async fn count_space_excess(pool: &PgPool, source: &str, keep: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT greatest(count(*) - $2, 0) FROM space_cache WHERE source = $1")
+            .bind(source)
+            .bind(keep)
+            .fetch_one(pool)
+            .await
+    }
+    
+    // Удаляет пачку строк источника сверх keep самых свежих
+    pub async fn delete_space_excess_batch(
+        pool: &PgPool,
+        source: &str,
+        keep: i64,
+        batch: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM space_cache WHERE id IN (
+                SELECT id FROM space_cache
+                WHERE source = $1
+                ORDER BY fetched_at DESC, id DESC
+                OFFSET $2 LIMIT $3
+             )"
+        )
+        .bind(source)
+        .bind(keep)
+        .bind(batch)
+        .execute(pool)
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    pub async fn log(
+        pool: &PgPool,
+        ran_at: DateTime<Utc>,
+        entry: &RetentionEntry,
+    ) -> Result<RetentionEntry, sqlx::Error> {
+        let row = sqlx::query(
+            "INSERT INTO retention_log (ran_at, target, source, policy, aggregated, deleted)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING id, ran_at, target, source, policy, aggregated, deleted"
+        )
+        .bind(ran_at)
+        .bind(&entry.target)
+        .bind(&entry.source)
+        .bind(&entry.policy)
+        .bind(entry.aggregated)
+        .bind(entry.deleted)
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(retention_entry_from_row(&row))
+    }
+    
+    pub async fn find_recent(pool: &PgPool, limit: i64) -> Result<Vec<RetentionEntry>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, ran_at, target, source, policy, aggregated, deleted
+             FROM retention_log
+             ORDER BY ran_at DESC, id DESC
+             LIMIT $1"
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(retention_entry_from_row).collect())
+    }
+}
+
+fn retention_entry_from_row(row: &sqlx::postgres::PgRow) -> RetentionEntry {
+    RetentionEntry {
+        id: Some(row.get("id")),
+        ran_at: Some(row.get("ran_at")),
+        target: row.get("target"),
+        source: row.get("source"),
+        policy: row.get("policy"),
+        aggregated: row.get("aggregated"),
+        deleted: row.get("deleted"),
+    }
+}
+
+pub struct NeoRepo;
+
+impl NeoRepo {
+    // Объекты и сближения одной ленты пишутся в одной транзакции.
+    // Возвращает количество сближений
+    pub async fn upsert(pool: &PgPool, objects: &[NeoObject]) -> Result<usize, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let mut approaches = 0;
+        
+        for neo in objects {
+            sqlx::query(
+                "INSERT INTO neo_objects (id, name, nasa_jpl_url, absolute_magnitude,
+                     diameter_min_km, diameter_max_km, hazardous, sentry)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (id) DO UPDATE SET
+                     name = EXCLUDED.name,
+                     nasa_jpl_url = EXCLUDED.nasa_jpl_url,
+                     absolute_magnitude = EXCLUDED.absolute_magnitude,
+                     diameter_min_km = EXCLUDED.diameter_min_km,
+                     diameter_max_km = EXCLUDED.diameter_max_km,
+                     hazardous = EXCLUDED.hazardous,
+                     sentry = EXCLUDED.sentry,
+                     updated_at = now()"
+            )
+            .bind(&neo.id)
+            .bind(&neo.name)
+            .bind(&neo.nasa_jpl_url)
+            .bind(neo.absolute_magnitude)
+            .bind(neo.diameter_min_km)
+            .bind(neo.diameter_max_km)
+            .bind(neo.hazardous)
+            .bind(neo.sentry)
+            .execute(&mut *tx)
+            .await?;
+            
+            for approach in &neo.approaches {
+                sqlx::query(
+                    "INSERT INTO neo_close_approaches (neo_id, approach_at,
+                         miss_distance_km, relative_velocity_kms, orbiting_body)
+                     VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (neo_id, approach_at) DO UPDATE SET
+                         miss_distance_km = EXCLUDED.miss_distance_km,
+                         relative_velocity_kms = EXCLUDED.relative_velocity_kms,
+                         orbiting_body = EXCLUDED.orbiting_body,
+                         updated_at = now()"
+                )
+                .bind(&neo.id)
+                .bind(approach.approach_at)
+                .bind(approach.miss_distance_km)
+                .bind(approach.relative_velocity_kms)
+                .bind(&approach.orbiting_body)
+                .execute(&mut *tx)
+                .await?;
+                approaches += 1;
+            }
         }
+        
+        tx.commit().await?;
+        Ok(approaches)
+    }
+    
+    pub async fn find_approaches(pool: &PgPool, query: &NeoQuery) -> Result<Vec<NeoApproach>, sqlx::Error> {
+        let order = match query.sort {
+            NeoSort::Approach => "a.approach_at ASC",
+            NeoSort::Miss => "a.miss_distance_km ASC NULLS LAST",
+            NeoSort::Velocity => "a.relative_velocity_kms DESC NULLS LAST",
+            NeoSort::Diameter => "o.diameter_max_km DESC NULLS LAST",
+        };
+        let rows = sqlx::query(&format!(
+            "SELECT o.id, o.name, o.nasa_jpl_url, o.hazardous, o.diameter_min_km, o.diameter_max_km,
+                    a.approach_at, a.miss_distance_km, a.relative_velocity_kms, a.orbiting_body
+             FROM neo_close_approaches a
+             JOIN neo_objects o ON o.id = a.neo_id
+             WHERE a.approach_at >= $1 AND a.approach_at < $2
+               AND ($3::BOOLEAN IS NULL OR o.hazardous = $3)
+               AND ($4::DOUBLE PRECISION IS NULL OR a.miss_distance_km <= $4)
+             ORDER BY {}, a.id
+             LIMIT $5",
+            order
+        ))
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.hazardous)
+        .bind(query.max_miss_km)
+        .bind(query.limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| NeoApproach {
+            neo_id: row.get("id"),
+            name: row.get("name"),
+            nasa_jpl_url: row.get("nasa_jpl_url"),
+            hazardous: row.get("hazardous"),
+            diameter_min_km: row.get("diameter_min_km"),
+            diameter_max_km: row.get("diameter_max_km"),
+            approach_at: row.get("approach_at"),
+            miss_distance_km: row.get("miss_distance_km"),
+            relative_velocity_kms: row.get("relative_velocity_kms"),
+            orbiting_body: row.get("orbiting_body"),
+        }).collect())
     }
 }
 
-pub async fn init_db(pool: &PgPool) -> Result<(), sqlx::Error> {
-    // ISS table
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS iss_fetch_log(
-            id BIGSERIAL PRIMARY KEY,
-            fetched_at TIMESTAMPTZ NOT NULL DEFAULT now(),
-            source_url TEXT NOT NULL,
-            payload JSONB NOT NULL
-        )"
-    )
-    .execute(pool)
-    .await?;
+// Связанные события в обе стороны: DONKI не всегда указывает связь у обоих событий
+const LINKED_IDS_SQL: &str = "ARRAY(
+    SELECT to_id FROM donki_links WHERE from_id = t.id
+    UNION
+    SELECT from_id FROM donki_links WHERE to_id = t.id
+    ORDER BY 1
+) AS linked_ids";
+
+const FLARE_COLUMNS: &str = "t.id, t.class_type, t.class_flux, t.begin_at, t.peak_at, t.end_at,
+    t.source_location, t.active_region, t.link";
+
+const CME_COLUMNS: &str = "t.id, t.start_at, t.speed_kms, t.half_angle_deg, t.latitude, t.longitude,
+    t.cme_type, t.source_location, t.active_region, t.note, t.link";
+
+pub struct DonkiRepo;
+
+impl DonkiRepo {
+    pub async fn upsert_flares(pool: &PgPool, flares: &[Flare]) -> Result<usize, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        
+        for flare in flares {
+            sqlx::query(
+                "INSERT INTO flares (id, class_type, class_flux, begin_at, peak_at, end_at,
+                     source_location, active_region, link)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (id) DO UPDATE SET
+                     class_type = EXCLUDED.class_type,
+                     class_flux = EXCLUDED.class_flux,
+                     begin_at = EXCLUDED.begin_at,
+                     peak_at = EXCLUDED.peak_at,
+                     end_at = EXCLUDED.end_at,
+                     source_location = EXCLUDED.source_location,
+                     active_region = EXCLUDED.active_region,
+                     link = EXCLUDED.link,
+                     updated_at = now()"
+            )
+            .bind(&flare.id)
+            .bind(&flare.class_type)
+            .bind(flare.class_flux)
+            .bind(flare.begin_at)
+            .bind(flare.peak_at)
+            .bind(flare.end_at)
+            .bind(&flare.source_location)
+            .bind(flare.active_region)
+            .bind(&flare.link)
+            .execute(&mut *tx)
+            .await?;
+            
+            replace_links(&mut tx, &flare.id, &flare.linked_ids).await?;
+        }
+        
+        tx.commit().await?;
+        Ok(flares.len())
+    }
     
-    // OSDR table
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS osdr_items(
-            id BIGSERIAL PRIMARY KEY,
-            dataset_id TEXT,
-            title TEXT,
-            status TEXT,
-            updated_at TIMESTAMPTZ,
-            inserted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
-            raw JSONB NOT NULL
-        )"
-    )
-    .execute(pool)
-    .await?;
+    pub async fn upsert_cmes(pool: &PgPool, cmes: &[Cme]) -> Result<usize, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        
+        for cme in cmes {
+            sqlx::query(
+                "INSERT INTO cmes (id, start_at, speed_kms, half_angle_deg, latitude, longitude,
+                     cme_type, source_location, active_region, note, link)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                 ON CONFLICT (id) DO UPDATE SET
+                     start_at = EXCLUDED.start_at,
+                     speed_kms = EXCLUDED.speed_kms,
+                     half_angle_deg = EXCLUDED.half_angle_deg,
+                     latitude = EXCLUDED.latitude,
+                     longitude = EXCLUDED.longitude,
+                     cme_type = EXCLUDED.cme_type,
+                     source_location = EXCLUDED.source_location,
+                     active_region = EXCLUDED.active_region,
+                     note = EXCLUDED.note,
+                     link = EXCLUDED.link,
+                     updated_at = now()"
+            )
+            .bind(&cme.id)
+            .bind(cme.start_at)
+            .bind(cme.speed_kms)
+            .bind(cme.half_angle_deg)
+            .bind(cme.latitude)
+            .bind(cme.longitude)
+            .bind(&cme.cme_type)
+            .bind(&cme.source_location)
+            .bind(cme.active_region)
+            .bind(&cme.note)
+            .bind(&cme.link)
+            .execute(&mut *tx)
+            .await?;
+            
+            replace_links(&mut tx, &cme.id, &cme.linked_ids).await?;
+        }
+        
+        tx.commit().await?;
+        Ok(cmes.len())
+    }
     
-    sqlx::query(
-        "CREATE UNIQUE INDEX IF NOT EXISTS ux_osdr_dataset_id
-         ON osdr_items(dataset_id) WHERE dataset_id IS NOT NULL"
-    )
-    .execute(pool)
-    .await?;
+    pub async fn find_flares(pool: &PgPool, query: &FlareQuery) -> Result<Vec<Flare>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {}
+             FROM flares t
+             WHERE t.peak_at >= $1 AND t.peak_at < $2
+               AND ($3::DOUBLE PRECISION IS NULL OR t.class_flux >= $3)
+             ORDER BY t.peak_at DESC, t.id
+             LIMIT $4",
+            FLARE_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.min_flux)
+        .bind(query.limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(flare_from_row).collect())
+    }
     
-    // Space cache table
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS space_cache(
-            id BIGSERIAL PRIMARY KEY,
-            source TEXT NOT NULL,
-            fetched_at TIMESTAMPTZ NOT NULL DEFAULT now(),
-            payload JSONB NOT NULL
-        )"
-    )
-    .execute(pool)
-    .await?;
+    pub async fn find_flare(pool: &PgPool, id: &str) -> Result<Option<Flare>, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT {}, {} FROM flares t WHERE t.id = $1",
+            FLARE_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.as_ref().map(flare_from_row))
+    }
+    
+    pub async fn find_cmes(pool: &PgPool, query: &CmeQuery) -> Result<Vec<Cme>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {}
+             FROM cmes t
+             WHERE t.start_at >= $1 AND t.start_at < $2
+               AND ($3::DOUBLE PRECISION IS NULL OR t.speed_kms >= $3)
+             ORDER BY t.start_at DESC, t.id
+             LIMIT $4",
+            CME_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.min_speed)
+        .bind(query.limit)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(cme_from_row).collect())
+    }
     
+    pub async fn find_cme(pool: &PgPool, id: &str) -> Result<Option<Cme>, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT {}, {} FROM cmes t WHERE t.id = $1",
+            CME_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.as_ref().map(cme_from_row))
+    }
+    
+    // CME, связанные с событием (обычно со вспышкой) в любую сторону
+    pub async fn find_cmes_linked_to(pool: &PgPool, id: &str) -> Result<Vec<Cme>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {}
+             FROM cmes t
+             WHERE t.id IN (
+                 SELECT to_id FROM donki_links WHERE from_id = $1
+                 UNION
+                 SELECT from_id FROM donki_links WHERE to_id = $1
+             )
+             ORDER BY t.start_at, t.id",
+            CME_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(cme_from_row).collect())
+    }
+    
+    pub async fn find_flares_linked_to(pool: &PgPool, id: &str) -> Result<Vec<Flare>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {}
+             FROM flares t
+             WHERE t.id IN (
+                 SELECT to_id FROM donki_links WHERE from_id = $1
+                 UNION
+                 SELECT from_id FROM donki_links WHERE to_id = $1
+             )
+             ORDER BY t.peak_at, t.id",
+            FLARE_COLUMNS, LINKED_IDS_SQL
+        ))
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(flare_from_row).collect())
+    }
+}
+
+// Набор связей события заменяется целиком: повторная загрузка того же ответа ничего не меняет
+async fn replace_links(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    from_id: &str,
+    to_ids: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM donki_links WHERE from_id = $1")
+        .bind(from_id)
+        .execute(&mut **tx)
+        .await?;
     sqlx::query(
-        "CREATE INDEX IF NOT EXISTS ix_space_cache_source 
-         ON space_cache(source, fetched_at DESC)"
+        "INSERT INTO donki_links (from_id, to_id)
+         SELECT $1, unnest($2::TEXT[])
+         ON CONFLICT DO NOTHING"
     )
-    .execute(pool)
+    .bind(from_id)
+    .bind(to_ids)
+    .execute(&mut **tx)
     .await?;
     
     Ok(())
 }
 
+pub struct WatermarkRepo;
+
+impl WatermarkRepo {
+    pub async fn find(pool: &PgPool, source: &str) -> Result<Option<NaiveDate>, sqlx::Error> {
+        sqlx::query_scalar("SELECT covered_to FROM fetch_watermarks WHERE source = $1")
+            .bind(source)
+            .fetch_optional(pool)
+            .await
+    }
+    
+    pub async fn find_all(pool: &PgPool) -> Result<Vec<FetchWatermark>, sqlx::Error> {
+        let rows = sqlx::query("SELECT source, covered_to, updated_at FROM fetch_watermarks ORDER BY source")
+            .fetch_all(pool)
+            .await?;
+        
+        Ok(rows.iter().map(|row| FetchWatermark {
+            source: row.get("source"),
+            covered_to: row.get("covered_to"),
+            updated_at: row.get("updated_at"),
+        }).collect())
+    }
+    
+    // Отметка только растёт: запоздавшее окно не откатывает её назад
+    pub async fn advance(pool: &PgPool, source: &str, covered_to: NaiveDate) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO fetch_watermarks (source, covered_to) VALUES ($1, $2)
+             ON CONFLICT (source) DO UPDATE SET
+                 covered_to = GREATEST(fetch_watermarks.covered_to, EXCLUDED.covered_to),
+                 updated_at = now()"
+        )
+        .bind(source)
+        .bind(covered_to)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+fn flare_from_row(row: &sqlx::postgres::PgRow) -> Flare {
+    Flare {
+        id: row.get("id"),
+        class_type: row.get("class_type"),
+        class_flux: row.get("class_flux"),
+        begin_at: row.get("begin_at"),
+        peak_at: row.get("peak_at"),
+        end_at: row.get("end_at"),
+        source_location: row.get("source_location"),
+        active_region: row.get("active_region"),
+        link: row.get("link"),
+        linked_ids: row.get("linked_ids"),
+    }
+}
+
+fn cme_from_row(row: &sqlx::postgres::PgRow) -> Cme {
+    Cme {
+        id: row.get("id"),
+        start_at: row.get("start_at"),
+        speed_kms: row.get("speed_kms"),
+        half_angle_deg: row.get("half_angle_deg"),
+        latitude: row.get("latitude"),
+        longitude: row.get("longitude"),
+        cme_type: row.get("cme_type"),
+        source_location: row.get("source_location"),
+        active_region: row.get("active_region"),
+        note: row.get("note"),
+        link: row.get("link"),
+        linked_ids: row.get("linked_ids"),
+    }
+}
@@ -1,10 +1,12 @@
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::Router;
 
 use crate::handlers::AppState;
 use crate::handlers::{
-    health, iss_trend, last_iss, osdr_list, osdr_sync, space_latest, space_refresh, space_summary,
-    trigger_iss,
+    cme_detail, cmes_list, flare_detail, flares_list, health, iss_history, iss_hourly, iss_passes,
+    iss_predict, iss_stream, iss_trend, job_runs, jobs_list, last_iss, neo_list, osdr_detail,
+    osdr_files, osdr_history, osdr_list, osdr_mapping, osdr_mapping_dry_run, osdr_search, osdr_sync, retention_log,
+    space_history, space_latest, space_refresh, space_snapshot, space_summary, trigger_iss, ws_connect,
 };
 
 pub fn create_router() -> Router<AppState> {
@@ -13,10 +15,32 @@ pub fn create_router() -> Router<AppState> {
         .route("/last", get(last_iss))
         .route("/fetch", get(trigger_iss))
         .route("/iss/trend", get(iss_trend))
+        .route("/iss/history", get(iss_history))
+        .route("/iss/hourly", get(iss_hourly))
+        .route("/iss/predict", get(iss_predict))
+        .route("/iss/passes", get(iss_passes))
+        .route("/iss/stream", get(iss_stream))
         .route("/osdr/sync", get(osdr_sync))
         .route("/osdr/list", get(osdr_list))
+        .route("/osdr/search", get(osdr_search))
+        .route("/osdr/mapping", get(osdr_mapping))
+        .route("/osdr/mapping/dry-run", post(osdr_mapping_dry_run))
+        .route("/osdr/:dataset_id", get(osdr_detail))
+        .route("/osdr/:dataset_id/history", get(osdr_history))
+        .route("/osdr/:dataset_id/files", get(osdr_files))
         .route("/space/:src/latest", get(space_latest))
+        .route("/space/:src/history", get(space_history))
+        .route("/space/:src/:id", get(space_snapshot))
         .route("/space/refresh", get(space_refresh))
         .route("/space/summary", get(space_summary))
+        .route("/neo", get(neo_list))
+        .route("/donki/flares", get(flares_list))
+        .route("/donki/flares/:id", get(flare_detail))
+        .route("/donki/cmes", get(cmes_list))
+        .route("/donki/cmes/:id", get(cme_detail))
+        .route("/jobs", get(jobs_list))
+        .route("/jobs/:name/runs", get(job_runs))
+        .route("/retention", get(retention_log))
+        .route("/ws", get(ws_connect))
 }
 
@@ -0,0 +1,342 @@
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use rand::Rng;
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+
+use crate::domain::ApiError;
+use crate::repo::JobRunRepo;
+use crate::state::AppState;
+
+pub const TRIGGER_SCHEDULED: &str = "scheduled";
+pub const TRIGGER_MANUAL: &str = "manual";
+pub const TRIGGER_CLI: &str = "cli";
+
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILED: &str = "failed";
+
+// Фоновая задача, запускаемая планировщиком
+#[async_trait]
+pub trait Job: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Ключ pg_advisory_lock, уникальный для каждой задачи
+    fn lock_key(&self) -> i64;
+
+    async fn run(&self, state: &AppState) -> Result<RunOutput, ApiError>;
+}
+
+// Итог запуска: сколько строк записано и сколько данных пришло без изменений
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunOutput {
+    pub written: usize,
+    pub unchanged: usize,
+}
+
+impl From<usize> for RunOutput {
+    fn from(written: usize) -> Self {
+        Self { written, unchanged: 0 }
+    }
+}
+
+impl From<crate::domain::OsdrSyncReport> for RunOutput {
+    fn from(report: crate::domain::OsdrSyncReport) -> Self {
+        Self { written: report.written(), unchanged: report.unchanged }
+    }
+}
+
+impl std::ops::Add for RunOutput {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            written: self.written + other.written,
+            unchanged: self.unchanged + other.unchanged,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    // Cron-выражение (если задано) имеет приоритет над интервалом
+    pub fn from_config(cron_expr: Option<&str>, every_secs: u64) -> Result<Self, ApiError> {
+        match cron_expr {
+            Some(expr) => {
+                let schedule = cron::Schedule::from_str(expr).map_err(|e| {
+                    ApiError::Validation(format!("invalid cron expression '{}': {}", expr, e))
+                })?;
+                Ok(Schedule::Cron(Box::new(schedule)))
+            }
+            None => Ok(Schedule::Every(Duration::from_secs(every_secs))),
+        }
+    }
+
+    fn next_delay(&self, first_run: bool) -> Duration {
+        match self {
+            // Интервальные задачи запускаются сразу при старте
+            Schedule::Every(_) if first_run => Duration::ZERO,
+            Schedule::Every(interval) => *interval,
+            Schedule::Cron(schedule) => schedule
+                .upcoming(Utc)
+                .next()
+                .and_then(|at| (at - Utc::now()).to_std().ok())
+                .unwrap_or(Duration::ZERO),
+        }
+    }
+
+    // Ожидаемый период между запусками (для cron - между двумя ближайшими)
+    pub fn period(&self) -> Duration {
+        match self {
+            Schedule::Every(interval) => *interval,
+            Schedule::Cron(schedule) => {
+                let mut upcoming = schedule.upcoming(Utc);
+                match (upcoming.next(), upcoming.next()) {
+                    (Some(a), Some(b)) => (b - a).to_std().unwrap_or(Duration::ZERO),
+                    _ => Duration::ZERO,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "every {}s", interval.as_secs()),
+            Schedule::Cron(schedule) => write!(f, "cron '{}'", schedule),
+        }
+    }
+}
+
+struct ScheduledJob {
+    job: Arc<dyn Job>,
+    schedule: Schedule,
+}
+
+#[derive(Clone, Debug)]
+pub struct JobInfo {
+    pub name: &'static str,
+    pub schedule: Schedule,
+}
+
+pub struct Scheduler {
+    jobs: Vec<ScheduledJob>,
+    jitter: Duration,
+    concurrency: usize,
+}
+
+impl Scheduler {
+    // concurrency ограничивает число одновременно работающих задач:
+    // каждая держит отдельное соединение под advisory lock
+    pub fn new(jitter: Duration, concurrency: usize) -> Self {
+        Self {
+            jobs: Vec::new(),
+            jitter,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    pub fn register(&mut self, job: impl Job + 'static, schedule: Schedule) -> Result<&mut Self, ApiError> {
+        if self.jobs.iter().any(|j| j.job.name() == job.name() || j.job.lock_key() == job.lock_key()) {
+            return Err(ApiError::Internal(format!(
+                "job '{}' registered twice or reuses a lock key",
+                job.name()
+            )));
+        }
+        self.jobs.push(ScheduledJob {
+            job: Arc::new(job),
+            schedule,
+        });
+        Ok(self)
+    }
+
+    pub fn catalog(&self) -> Vec<JobInfo> {
+        self.jobs
+            .iter()
+            .map(|j| JobInfo {
+                name: j.job.name(),
+                schedule: j.schedule.clone(),
+            })
+            .collect()
+    }
+
+    pub fn lock_keys(&self) -> Vec<(&'static str, i64)> {
+        self.jobs.iter().map(|j| (j.job.name(), j.job.lock_key())).collect()
+    }
+
+    // stop прекращает планирование новых запусков, abort прерывает уже идущие
+    pub fn start(self, state: AppState, stop: CancellationToken, abort: CancellationToken) -> TaskTracker {
+        let tracker = TaskTracker::new();
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+
+        for scheduled in self.jobs {
+            let state = state.clone();
+            let stop = stop.clone();
+            let abort = abort.clone();
+            let permits = permits.clone();
+            let jitter = self.jitter;
+            info!("scheduling job '{}' ({})", scheduled.job.name(), scheduled.schedule);
+
+            tracker.spawn(async move {
+                let mut first_run = true;
+                loop {
+                    let delay = scheduled.schedule.next_delay(first_run) + random_jitter(jitter);
+                    first_run = false;
+
+                    tokio::select! {
+                        _ = stop.cancelled() => break,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+
+                    let _permit = tokio::select! {
+                        _ = stop.cancelled() => break,
+                        permit = permits.acquire() => match permit {
+                            Ok(permit) => permit,
+                            Err(_) => break,
+                        },
+                    };
+                    run_locked(&state, scheduled.job.as_ref(), &abort).await;
+                }
+                debug!("job '{}' stopped", scheduled.job.name());
+            });
+        }
+
+        tracker.close();
+        tracker
+    }
+}
+
+async fn run_locked(state: &AppState, job: &dyn Job, abort: &CancellationToken) {
+    let run = async {
+        tokio::select! {
+            result = job.run(state) => result,
+            _ = abort.cancelled() => {
+                warn!("job '{}' interrupted by shutdown", job.name());
+                Err(ApiError::Internal("interrupted by shutdown".to_string()))
+            }
+        }
+    };
+    // Ошибки запуска уже записаны в job_runs и в лог
+    if let Err(ApiError::AlreadyRunning(_)) =
+        run_exclusive(&state.pool, job.lock_key(), job.name(), TRIGGER_SCHEDULED, run).await
+    {
+        debug!("job '{}' is running elsewhere, skipping", job.name());
+    }
+}
+
+// Запуск под advisory lock задачи: планировщик, ручной запуск по HTTP и CLI
+// не пересекаются. Если lock занят, возвращает ApiError::AlreadyRunning без
+// записи в job_runs. Блокировка берётся и снимается на одном и том же соединении,
+// иначе pg_advisory_unlock может уйти в другую сессию пула
+pub async fn run_exclusive<F, T>(
+    pool: &PgPool,
+    lock_key: i64,
+    job_name: &str,
+    trigger: &str,
+    run: F,
+) -> Result<T, ApiError>
+where
+    F: Future<Output = Result<T, ApiError>>,
+    T: Into<RunOutput> + Copy,
+{
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("job '{}': cannot acquire connection: {:?}", job_name, e);
+        ApiError::Database(e)
+    })?;
+
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+        .bind(lock_key)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("job '{}': advisory lock error: {:?}", job_name, e);
+            ApiError::Database(e)
+        })?;
+    if !locked {
+        return Err(ApiError::AlreadyRunning(job_name.to_string()));
+    }
+
+    let result = record_run(pool, job_name, trigger, run).await;
+    let _: Result<_, _> = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(lock_key)
+        .execute(&mut *conn)
+        .await;
+    result
+}
+
+// Выполняет задачу и сохраняет результат в job_runs
+pub async fn record_run<F, T>(
+    pool: &PgPool,
+    job_name: &str,
+    trigger: &str,
+    run: F,
+) -> Result<T, ApiError>
+where
+    F: Future<Output = Result<T, ApiError>>,
+    T: Into<RunOutput> + Copy,
+{
+    let run_id = JobRunRepo::start(pool, job_name, trigger)
+        .await
+        .map_err(|e| error!("job '{}': cannot record run start: {:?}", job_name, e))
+        .ok();
+
+    let result = run.await;
+
+    let finished = match &result {
+        Ok(output) => {
+            let output: RunOutput = (*output).into();
+            (OUTCOME_SUCCESS, None, Some((output.written as i64, output.unchanged as i64)))
+        }
+        Err(e) => {
+            error!("job '{}' failed: {:?}", job_name, e);
+            (OUTCOME_FAILED, Some(redact_api_key(&e.to_string())), None)
+        }
+    };
+
+    if let Some(id) = run_id {
+        let (outcome, err, rows) = finished;
+        if let Err(e) = JobRunRepo::finish(pool, id, outcome, err.as_deref(), rows).await {
+            error!("job '{}': cannot record run finish: {:?}", job_name, e);
+        }
+    }
+
+    result
+}
+
+// Текст ошибки отдаётся через /jobs, ключ NASA из URL туда попадать не должен
+pub fn redact_api_key(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("api_key=") {
+        let (head, tail) = rest.split_at(pos + "api_key=".len());
+        out.push_str(head);
+        out.push_str("***");
+        let end = tail
+            .find(['&', ')', ' ', '"'])
+            .unwrap_or(tail.len());
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn random_jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max.as_millis() as u64))
+}
@@ -1,39 +1,197 @@
-use chrono::{DateTime, Days, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use serde_json::Value;
 use sqlx::PgPool;
 
+use std::collections::HashMap;
 use std::sync::Arc;
-use crate::clients::{IssClient, NasaClient, SpaceXClient};
-use crate::domain::ApiError;
-use crate::repo::{CacheRepo, IssRepo, OsdrRepo};
+use futures::Stream;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use crate::events::{space_topic, ChangeEvent, EventBus, TOPIC_ISS, TOPIC_OSDR};
+use crate::clients::{IssClient, NasaClient, SpaceXClient, TleClient};
+use crate::domain::{
+    ApiError, BackfillReport, IssFetchLog, OsdrRecord, OsdrSearchQuery, OsdrSort, OsdrSyncReport, RetentionEntry,
+    RetentionReport,
+};
+use crate::config::RetentionPolicy;
+use crate::mapping::OsdrMapping;
+use crate::orbit::{Observer, PassPoint, Sgp4, Tle};
+use crate::repo::{
+    CacheRepo, CacheWrite, DataTable, DonkiRepo, IssRepo, JobRunRepo, MaintenanceRepo, NeoRepo, OsdrRepo, RetentionRepo,
+    TleRepo, WatermarkRepo,
+};
+use crate::scheduler::{JobInfo, RunOutput};
+
+const STREAM_REPLAY_BATCH: i64 = 500;
+const OSDR_SYNC_BATCH: usize = 500;
+// NeoWs отдаёт ленту не длиннее 7 дней; DONKI режется на такие же окна
+const FEED_WINDOW_DAYS: u64 = 7;
 
 pub struct IssService {
-    repo: IssRepo,
     client: Arc<IssClient>,
     pool: PgPool,
+    events: EventBus,
 }
 
 impl IssService {
-    pub fn new(pool: PgPool, client: Arc<IssClient>) -> Self {
+    pub fn new(pool: PgPool, client: Arc<IssClient>, events: EventBus) -> Self {
         Self {
-            repo: IssRepo,
             client,
             pool,
+            events,
         }
     }
     
-    pub async fn fetch_and_store(&self) -> Result<(), ApiError> {
+    pub async fn fetch_and_store(&self) -> Result<usize, ApiError> {
         let json = self.client.fetch_current().await?;
         let source_url = self.client.base_url.clone();
         
-        IssRepo::create(&self.pool, &source_url, json).await?;
-        Ok(())
+        let position = parse_iss_position(&json);
+        if position.is_none() {
+            tracing::warn!("ISS payload from {} has no usable position", source_url);
+        }
+        
+        let log = IssRepo::create(&self.pool, &source_url, json, position.as_ref()).await?;
+        self.events.publish(ChangeEvent::new(
+            TOPIC_ISS,
+            Some(log.id),
+            serde_json::json!({ "position": log.position }),
+        )).await;
+        Ok(1)
+    }
+    
+    // Поток новых записей для /iss/stream. Сначала подписываемся на шину,
+    // затем досылаем из БД всё после after_id; событие шины лишь сообщает,
+    // что появились новые строки, сами записи всегда читаются из БД по id
+    pub fn stream(&self, after_id: Option<i64>, stop: CancellationToken) -> impl Stream<Item = IssFetchLog> {
+        let pool = self.pool.clone();
+        let mut events = self.events.subscribe();
+        
+        async_stream::stream! {
+            let mut last_id = after_id;
+            let mut replay = after_id.is_some();
+            loop {
+                while replay {
+                    let batch = match IssRepo::find_after(&pool, last_id.unwrap_or(0), STREAM_REPLAY_BATCH).await {
+                        Ok(batch) => batch,
+                        Err(e) => {
+                            // Клиент переподключится с Last-Event-ID
+                            tracing::error!("ISS stream replay failed: {:?}", e);
+                            return;
+                        }
+                    };
+                    replay = batch.len() as i64 == STREAM_REPLAY_BATCH;
+                    for log in batch {
+                        last_id = Some(log.id);
+                        yield log;
+                    }
+                }
+                
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    event = events.recv() => match event {
+                        Ok(event) if event.topic == TOPIC_ISS => {
+                            if last_id.is_none() {
+                                last_id = event.id.map(|id| id - 1);
+                            }
+                            replay = last_id.is_some();
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("ISS stream subscriber lagged by {} events", skipped);
+                            replay = last_id.is_some();
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }
+        }
     }
     
     pub async fn get_last(&self) -> Result<Option<crate::domain::IssFetchLog>, ApiError> {
         Ok(IssRepo::find_last(&self.pool).await?)
     }
     
+    pub async fn history(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_points: usize,
+    ) -> Result<crate::domain::IssHistory, ApiError> {
+        let points = IssRepo::find_range(&self.pool, from, to).await?;
+        let total = points.len();
+        let points = lttb(points, max_points);
+        
+        Ok(crate::domain::IssHistory {
+            from,
+            to,
+            total,
+            returned: points.len(),
+            points,
+        })
+    }
+    
+    // Почасовые агрегаты, в которые свёрнуты сырые данные старше ISS_RAW_RETENTION
+    pub async fn hourly(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<crate::domain::IssHourly>, ApiError> {
+        Ok(IssRepo::find_hourly(&self.pool, from, to).await?)
+    }
+    
+    // Статистика по всем точкам окна; разрывом считается интервал
+    // между соседними замерами больше полутора ожидаемых
+    pub async fn calculate_window_trend(
+        &self,
+        window: chrono::Duration,
+        expected_interval: std::time::Duration,
+    ) -> Result<crate::domain::IssWindowTrend, ApiError> {
+        let to = Utc::now();
+        let points = IssRepo::find_range(&self.pool, to - window, to).await?;
+        
+        let expected_sec = expected_interval.as_secs_f64();
+        let mut distance_km = 0.0;
+        let mut gaps = Vec::new();
+        for pair in points.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            distance_km += haversine_km(a.latitude, a.longitude, b.latitude, b.longitude);
+            
+            let dt_sec = (b.at - a.at).num_milliseconds() as f64 / 1000.0;
+            if expected_sec > 0.0 && dt_sec > expected_sec * 1.5 {
+                gaps.push(crate::domain::IssSamplingGap {
+                    from_time: a.at,
+                    to_time: b.at,
+                    dt_sec,
+                });
+            }
+        }
+        
+        let velocities: Vec<f64> = points.iter().filter_map(|p| p.velocity).collect();
+        let altitudes: Vec<f64> = points.iter().filter_map(|p| p.altitude).collect();
+        
+        let heading_deg = match points.as_slice() {
+            [.., a, b] => Some(bearing_deg(a.latitude, a.longitude, b.latitude, b.longitude)),
+            _ => None,
+        };
+        
+        Ok(crate::domain::IssWindowTrend {
+            window_sec: window.num_seconds(),
+            from_time: points.first().map(|p| p.at),
+            to_time: points.last().map(|p| p.at),
+            samples: points.len(),
+            distance_km,
+            mean_velocity_kmh: (!velocities.is_empty())
+                .then(|| velocities.iter().sum::<f64>() / velocities.len() as f64),
+            max_velocity_kmh: velocities.iter().copied().reduce(f64::max),
+            min_altitude_km: altitudes.iter().copied().reduce(f64::min),
+            max_altitude_km: altitudes.iter().copied().reduce(f64::max),
+            heading_deg,
+            expected_interval_sec: expected_interval.as_secs(),
+            gaps,
+        })
+    }
+    
     pub async fn calculate_trend(&self) -> Result<crate::domain::IssTrend, ApiError> {
         let logs = IssRepo::find_last_two(&self.pool).await?;
         
@@ -54,14 +212,14 @@ impl IssService {
         
         let t2 = logs[0].fetched_at;
         let t1 = logs[1].fetched_at;
-        let p2 = &logs[0].payload;
-        let p1 = &logs[1].payload;
+        let p2 = logs[0].position.as_ref();
+        let p1 = logs[1].position.as_ref();
         
-        let lat1 = extract_number(&p1["latitude"]);
-        let lon1 = extract_number(&p1["longitude"]);
-        let lat2 = extract_number(&p2["latitude"]);
-        let lon2 = extract_number(&p2["longitude"]);
-        let v2 = extract_number(&p2["velocity"]);
+        let lat1 = p1.map(|p| p.latitude);
+        let lon1 = p1.map(|p| p.longitude);
+        let lat2 = p2.map(|p| p.latitude);
+        let lon2 = p2.map(|p| p.longitude);
+        let v2 = p2.and_then(|p| p.velocity);
         
         let mut delta_km = 0.0;
         let mut movement = false;
@@ -88,114 +246,917 @@ impl IssService {
     }
 }
 
+pub struct OrbitService {
+    client: Arc<TleClient>,
+    pool: PgPool,
+}
+
+impl OrbitService {
+    pub fn new(pool: PgPool, client: Arc<TleClient>) -> Self {
+        Self { client, pool }
+    }
+    
+    pub async fn fetch_and_store_tle(&self) -> Result<usize, ApiError> {
+        let (text, source) = self.client.fetch_tle().await?;
+        let tle = Tle::parse(&text).map_err(|e| ApiError::Internal(e.to_string()))?;
+        // Проверяем, что набор пригоден для SGP4, до сохранения
+        Sgp4::new(&tle).map_err(|e| ApiError::Internal(e.to_string()))?;
+        
+        let written = TleRepo::create(
+            &self.pool,
+            tle.norad_id,
+            tle.name.as_deref(),
+            &tle.line1,
+            &tle.line2,
+            tle.epoch,
+            &source,
+        ).await?;
+        Ok(written as usize)
+    }
+    
+    // Прогноз по TLE с эпохой, ближайшей к середине интервала; для прошедшей
+    // части интервала прогноз сравнивается с фактическими замерами iss_fetch_log
+    pub async fn predict(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step: chrono::Duration,
+    ) -> Result<crate::domain::IssPrediction, ApiError> {
+        let tle_set = TleRepo::find_nearest(&self.pool, from + (to - from) / 2).await?
+            .ok_or(ApiError::NotFound)?;
+        let tle = Tle::from_lines(tle_set.name.clone(), &tle_set.line1, &tle_set.line2)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        let sgp4 = Sgp4::new(&tle).map_err(|e| ApiError::Internal(e.to_string()))?;
+        
+        let mut points = Vec::new();
+        let mut at = from;
+        while at <= to {
+            let (geo, speed) = sgp4.subpoint_at(at).map_err(|e| ApiError::Internal(e.to_string()))?;
+            points.push(crate::domain::IssPredictedPoint {
+                at,
+                latitude: geo.latitude,
+                longitude: geo.longitude,
+                altitude: geo.altitude_km,
+                velocity: speed,
+            });
+            at += step;
+        }
+        
+        let samples = IssRepo::find_range(&self.pool, from, to).await?;
+        let errors: Vec<f64> = samples.iter().filter_map(|s| {
+            let (geo, _) = sgp4.subpoint_at(s.at).ok()?;
+            let ground = haversine_km(s.latitude, s.longitude, geo.latitude, geo.longitude);
+            let vertical = s.altitude.map_or(0.0, |alt| alt - geo.altitude_km);
+            Some((ground * ground + vertical * vertical).sqrt())
+        }).collect();
+        
+        let accuracy = (!errors.is_empty()).then(|| crate::domain::PredictionAccuracy {
+            samples: errors.len(),
+            mean_error_km: errors.iter().sum::<f64>() / errors.len() as f64,
+            max_error_km: errors.iter().copied().fold(0.0, f64::max),
+            rms_error_km: (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt(),
+        });
+        
+        Ok(crate::domain::IssPrediction {
+            tle: tle_set,
+            from,
+            to,
+            step_sec: step.num_seconds(),
+            points,
+            accuracy,
+        })
+    }
+    
+    // Пролёты МКС над наблюдателем на ближайшие дни по самому свежему TLE
+    pub async fn passes(
+        &self,
+        observer: Observer,
+        days: i64,
+        min_elevation: f64,
+    ) -> Result<crate::domain::IssPasses, ApiError> {
+        let from = Utc::now();
+        let to = from + chrono::Duration::days(days);
+        let tle_set = TleRepo::find_nearest(&self.pool, from + (to - from) / 2).await?
+            .ok_or(ApiError::NotFound)?;
+        let tle = Tle::from_lines(tle_set.name.clone(), &tle_set.line1, &tle_set.line2)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        let sgp4 = Sgp4::new(&tle).map_err(|e| ApiError::Internal(e.to_string()))?;
+        
+        let passes = sgp4.passes(&observer, from, to, min_elevation)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        
+        let event = |p: PassPoint| crate::domain::IssPassEvent {
+            at: p.at,
+            azimuth: p.azimuth,
+            elevation: p.elevation,
+            range_km: p.range_km,
+        };
+        
+        Ok(crate::domain::IssPasses {
+            latitude: observer.latitude,
+            longitude: observer.longitude,
+            altitude_m: observer.altitude_km * 1000.0,
+            from,
+            to,
+            min_elevation,
+            tle_epoch: tle_set.epoch,
+            passes: passes.into_iter().map(|p| crate::domain::IssPass {
+                max_elevation: p.culmination.elevation,
+                duration_sec: (p.set.at - p.rise.at).num_seconds(),
+                visible: p.visible,
+                rise: event(p.rise),
+                culmination: event(p.culmination),
+                set: event(p.set),
+            }).collect(),
+        })
+    }
+}
+
 pub struct OsdrService {
-    repo: OsdrRepo,
     client: Arc<NasaClient>,
     pool: PgPool,
+    events: EventBus,
+    mapping: Arc<OsdrMapping>,
 }
 
 impl OsdrService {
-    pub fn new(pool: PgPool, client: Arc<NasaClient>) -> Self {
+    pub fn new(pool: PgPool, client: Arc<NasaClient>, events: EventBus, mapping: Arc<OsdrMapping>) -> Self {
         Self {
-            repo: OsdrRepo,
             client,
             pool,
+            events,
+            mapping,
         }
     }
     
-    pub async fn sync(&self) -> Result<usize, ApiError> {
-        let json = self.client.fetch_osdr().await?;
+    // Метаданные заглушек листинга запрашиваются лениво: для новых датасетов и тех,
+    // что не обновлялись дольше OSDR_DETAIL_REFRESH, у остальных берётся сохранённый raw.
+    // Событие публикуется, только если что-то изменилось
+    pub async fn sync(&self) -> Result<OsdrSyncReport, ApiError> {
+        let listing = self.client.fetch_osdr_listing().await?;
         
-        let items = extract_array(&json);
-        let mut written = 0;
+        let stub_ids: Vec<&str> = listing
+            .iter()
+            .filter(|e| e.detail_url.is_some())
+            .filter_map(|e| e.dataset_id.as_deref())
+            .collect();
+        let stored = OsdrRepo::find_details(&self.pool, &stub_ids).await?;
+        let fresh_since = self.client.osdr.detail_refresh.map(|refresh| Utc::now() - refresh);
+        let mut due: HashMap<String, String> = HashMap::new();
+        for entry in &listing {
+            if let (Some(id), Some(url)) = (&entry.dataset_id, &entry.detail_url) {
+                let fresh = match (stored.get(id), fresh_since) {
+                    (Some((_, Some(fetched_at))), Some(since)) => *fetched_at >= since,
+                    _ => false,
+                };
+                if !fresh {
+                    due.insert(id.clone(), url.clone());
+                }
+            }
+        }
+        let fetched: HashMap<String, Result<Value, ApiError>> =
+            self.client.fetch_osdr_details(due.into_iter().collect()).await.into_iter().collect();
         
-        for item in items {
-            let dataset_id = extract_string(&item, &["dataset_id", "id", "uuid", "studyId", "accession", "osdr_id"]);
-            let title = extract_string(&item, &["title", "name", "label"]);
-            let status = extract_string(&item, &["status", "state", "lifecycle"]);
-            let updated_at = extract_datetime(&item, &["updated", "updated_at", "modified", "lastUpdated", "timestamp"]);
-            
-            OsdrRepo::upsert(
-                &self.pool,
-                dataset_id.as_deref(),
-                title.as_deref(),
-                status.as_deref(),
-                updated_at,
-                item,
-            ).await?;
-            
-            written += 1;
+        // Повтор dataset_id в выдаче: остаётся последний, как и при построчном upsert
+        let mut records: Vec<OsdrRecord> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut failed = 0;
+        let mut skipped = 0;
+        for entry in listing {
+            let (item, details_fetched) = match (&entry.dataset_id, &entry.detail_url) {
+                (Some(id), Some(_)) => match (fetched.get(id), stored.get(id)) {
+                    (Some(Ok(detail)), _) => (detail.clone(), true),
+                    // Без свежих метаданных известный датасет остаётся как был, а не пропадает
+                    (Some(Err(e)), Some((raw, _))) => {
+                        tracing::warn!("OSDR dataset {}: metadata fetch failed, keeping stored: {}", id, e);
+                        (raw.clone(), false)
+                    }
+                    (None, Some((raw, _))) => (raw.clone(), false),
+                    (Some(Err(e)), None) => {
+                        tracing::warn!("OSDR dataset {}: metadata fetch failed, skipped: {}", id, e);
+                        failed += 1;
+                        continue;
+                    }
+                    (None, None) => continue,
+                },
+                _ => (entry.item, false),
+            };
+            let mut record = self.mapping.apply(item);
+            record.dataset_id = entry.dataset_id.or(record.dataset_id);
+            record.details_fetched = details_fetched;
+            // Без dataset_id запись нельзя ни обновить, ни отметить пропавшей -
+            // при каждой синхронизации она вставлялась бы заново
+            let Some(id) = record.dataset_id.clone() else {
+                skipped += 1;
+                continue;
+            };
+            match positions.get(&id) {
+                Some(&i) => records[i] = record,
+                None => {
+                    positions.insert(id, records.len());
+                    records.push(record);
+                }
+            }
+        }
+        if skipped > 0 {
+            tracing::warn!("OSDR sync: {} records without dataset_id skipped", skipped);
+        }
+        if records.is_empty() {
+            tracing::warn!("OSDR listing is empty, datasets are not marked missing");
         }
         
-        Ok(written)
+        let mut report = OsdrRepo::sync(&self.pool, &records, OSDR_SYNC_BATCH).await?;
+        report.failed = failed;
+        report.skipped = skipped;
+        if report.written() > 0 || report.missing > 0 {
+            self.events.publish(ChangeEvent::new(
+                TOPIC_OSDR,
+                None,
+                serde_json::json!({
+                    "written": report.written(),
+                    "inserted": report.inserted,
+                    "updated": report.updated,
+                    "missing": report.missing,
+                }),
+            )).await;
+        }
+        Ok(report)
     }
     
     pub async fn list(&self, limit: i64) -> Result<Vec<crate::domain::OsdrItem>, ApiError> {
         Ok(OsdrRepo::list(&self.pool, limit).await?)
     }
     
+    // Датасет, число его версий и время последнего изменения
+    pub async fn get(
+        &self,
+        dataset_id: &str,
+    ) -> Result<(crate::domain::OsdrItem, i64, Option<DateTime<Utc>>), ApiError> {
+        let item = OsdrRepo::find_by_dataset_id(&self.pool, dataset_id)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        let (versions, last_changed_at) = OsdrRepo::version_stats(&self.pool, item.id).await?;
+        Ok((item, versions, last_changed_at))
+    }
+    
+    pub async fn history(
+        &self,
+        dataset_id: &str,
+        before: Option<i32>,
+        limit: i64,
+        with_raw: bool,
+    ) -> Result<Vec<crate::domain::OsdrItemVersion>, ApiError> {
+        let item = OsdrRepo::find_by_dataset_id(&self.pool, dataset_id)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        Ok(OsdrRepo::find_versions(&self.pool, item.id, before, limit, with_raw).await?)
+    }
+    
+    // Как payload разложится по колонкам osdr_items; profile - проверить профиль до выкладки
+    pub fn dry_run_mapping(&self, items: &[Value], profile: Option<&OsdrMapping>) -> Value {
+        let mapping = profile.unwrap_or(&self.mapping);
+        serde_json::json!({
+            "profile": if profile.is_some() { "request" } else { "active" },
+            "items": items.iter().map(|item| mapping.dry_run(item)).collect::<Vec<_>>(),
+        })
+    }
+    
+    pub fn mapping(&self) -> &OsdrMapping {
+        &self.mapping
+    }
+    
+    // Файлы не хранятся: список запрашивается у OSDR по ссылке из метаданных датасета
+    pub async fn files(&self, dataset_id: &str) -> Result<Value, ApiError> {
+        let item = OsdrRepo::find_by_dataset_id(&self.pool, dataset_id)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        let url = item.raw
+            .pointer("/files/REST_URL")
+            .and_then(|u| u.as_str())
+            .ok_or(ApiError::NotFound)?;
+        self.client.fetch_osdr_files(dataset_id, url).await
+    }
+    
+    // Лишняя строка в запросе показывает, есть ли следующая страница
+    pub async fn search(&self, query: &OsdrSearchQuery) -> Result<crate::domain::OsdrSearchPage, ApiError> {
+        let probe = OsdrSearchQuery { limit: query.limit + 1, ..query.clone() };
+        let mut items = OsdrRepo::search(&self.pool, &probe).await?;
+        let next_cursor = if items.len() as i64 > query.limit {
+            items.truncate(query.limit as usize);
+            items.last().map(|hit| match query.sort {
+                OsdrSort::Relevance => format!("{}_{}", hit.rank.unwrap_or_default(), hit.item.id),
+                OsdrSort::Updated => {
+                    let at = hit.item.updated_at.map_or(0, |t| t.timestamp_micros());
+                    format!("{}_{}", at, hit.item.id)
+                }
+                OsdrSort::Inserted => format!("{}_{}", hit.item.inserted_at.timestamp_micros(), hit.item.id),
+            })
+        } else {
+            None
+        };
+        
+        Ok(crate::domain::OsdrSearchPage { items, next_cursor })
+    }
+    
     pub async fn count(&self) -> Result<i64, ApiError> {
         Ok(OsdrRepo::count(&self.pool).await?)
     }
 }
 
 pub struct SpaceCacheService {
-    repo: CacheRepo,
     nasa_client: Arc<NasaClient>,
     spacex_client: Arc<SpaceXClient>,
     pool: PgPool,
+    events: EventBus,
 }
 
 impl SpaceCacheService {
-    pub fn new(pool: PgPool, nasa_client: Arc<NasaClient>, spacex_client: Arc<SpaceXClient>) -> Self {
+    pub fn new(
+        pool: PgPool,
+        nasa_client: Arc<NasaClient>,
+        spacex_client: Arc<SpaceXClient>,
+        events: EventBus,
+    ) -> Self {
         Self {
-            repo: CacheRepo,
             nasa_client,
             spacex_client,
             pool,
+            events,
         }
     }
     
-    pub async fn fetch_apod(&self) -> Result<(), ApiError> {
+    pub async fn fetch_apod(&self) -> Result<RunOutput, ApiError> {
         let json = self.nasa_client.fetch_apod().await?;
-        CacheRepo::create(&self.pool, "apod", json).await?;
-        Ok(())
+        self.store("apod", json).await
     }
     
-    pub async fn fetch_neo(&self) -> Result<(), ApiError> {
+    pub async fn fetch_neo(&self) -> Result<RunOutput, ApiError> {
+        self.fetch_incremental("neo", 2).await
+    }
+    
+    pub async fn fetch_donki_flr(&self) -> Result<RunOutput, ApiError> {
+        self.fetch_incremental("flr", 5).await
+    }
+    
+    pub async fn fetch_donki_cme(&self) -> Result<RunOutput, ApiError> {
+        self.fetch_incremental("cme", 5).await
+    }
+    
+    // Загрузка от отметки fetch_watermarks до сегодня; без отметки - за последние
+    // first_days дней. День отметки запрашивается повторно, данные за него могли быть
+    // неполными. После простоя пропуск догружается окнами, отметка сдвигается после
+    // каждого, так что прерванная догрузка продолжится с того же места
+    async fn fetch_incremental(&self, source: &str, first_days: u64) -> Result<RunOutput, ApiError> {
         let today = Utc::now().date_naive();
-        let start = today - Days::new(2);
-        let json = self.nasa_client.fetch_neo_feed(&start.to_string(), &today.to_string()).await?;
-        CacheRepo::create(&self.pool, "neo", json).await?;
-        Ok(())
+        let from = match WatermarkRepo::find(&self.pool, source).await? {
+            Some(covered_to) => covered_to.min(today),
+            None => today - Days::new(first_days),
+        };
+        
+        let windows = date_windows(from, today);
+        let last = windows.len() - 1;
+        let mut output = RunOutput::default();
+        for (i, (start, end)) in windows.into_iter().enumerate() {
+            let (json, records) = self.fetch_window(source, start, end).await?;
+            tracing::debug!("{} {}..{}: {} record(s)", source, start, end, records);
+            // Снимок в space_cache - только по окну, заканчивающемуся сегодня
+            if i == last {
+                output = self.store(source, json).await?;
+            }
+            WatermarkRepo::advance(&self.pool, source, end).await?;
+        }
+        Ok(output)
     }
     
-    pub async fn fetch_donki_flr(&self) -> Result<(), ApiError> {
-        let (from, to) = last_days(5);
-        let json = self.nasa_client.fetch_donki_flr(&from, &to).await?;
-        CacheRepo::create(&self.pool, "flr", json).await?;
-        Ok(())
+    // Догрузка произвольного диапазона, только в типизированные таблицы: последний
+    // снимок в space_cache остаётся за плановой загрузкой. Отметка сдвигается, лишь
+    // пока диапазон примыкает к уже загруженному, иначе между ними остался бы пропуск
+    pub async fn backfill(&self, source: &str, from: NaiveDate, to: NaiveDate) -> Result<BackfillReport, ApiError> {
+        let mut covered = WatermarkRepo::find(&self.pool, source).await?;
+        let mut report = BackfillReport {
+            source: source.to_string(),
+            from,
+            to,
+            windows: 0,
+            records: 0,
+            watermark: covered,
+        };
+        
+        for (start, end) in date_windows(from, to) {
+            let (_, records) = self.fetch_window(source, start, end).await?;
+            tracing::info!("backfill {} {}..{}: {} record(s)", source, start, end, records);
+            report.windows += 1;
+            report.records += records;
+            
+            if covered.is_some_and(|c| start <= c + Days::new(1) && end > c) {
+                WatermarkRepo::advance(&self.pool, source, end).await?;
+                covered = Some(end);
+            }
+        }
+        
+        report.watermark = covered;
+        Ok(report)
     }
     
-    pub async fn fetch_donki_cme(&self) -> Result<(), ApiError> {
-        let (from, to) = last_days(5);
-        let json = self.nasa_client.fetch_donki_cme(&from, &to).await?;
-        CacheRepo::create(&self.pool, "cme", json).await?;
-        Ok(())
+    // Одно окно дат: запрос, разбор и upsert в типизированные таблицы.
+    // Upsert идёт и при неизменном содержимом: он идемпотентен, а таблицы могли
+    // появиться позже блоба. Возвращает ответ и количество разобранных записей
+    async fn fetch_window(&self, source: &str, from: NaiveDate, to: NaiveDate) -> Result<(Value, usize), ApiError> {
+        let (from, to) = (from.to_string(), to.to_string());
+        match source {
+            "neo" => {
+                let json = self.nasa_client.fetch_neo_feed(&from, &to).await?;
+                let objects = parse_neo_feed(&json);
+                let approaches = NeoRepo::upsert(&self.pool, &objects).await?;
+                Ok((json, approaches))
+            }
+            "flr" => {
+                let json = self.nasa_client.fetch_donki_flr(&from, &to).await?;
+                let flares: Vec<_> = extract_array(&json).iter().filter_map(parse_flare).collect();
+                DonkiRepo::upsert_flares(&self.pool, &flares).await?;
+                Ok((json, flares.len()))
+            }
+            "cme" => {
+                let json = self.nasa_client.fetch_donki_cme(&from, &to).await?;
+                let cmes: Vec<_> = extract_array(&json).iter().filter_map(parse_cme).collect();
+                DonkiRepo::upsert_cmes(&self.pool, &cmes).await?;
+                Ok((json, cmes.len()))
+            }
+            _ => Err(ApiError::Validation(format!("{} is not fetched by date", source))),
+        }
     }
     
-    pub async fn fetch_spacex(&self) -> Result<(), ApiError> {
+    pub async fn fetch_spacex(&self) -> Result<RunOutput, ApiError> {
         let json = self.spacex_client.fetch_next_launch().await?;
-        CacheRepo::create(&self.pool, "spacex", json).await?;
-        Ok(())
+        self.store("spacex", json).await
+    }
+    
+    // Повтор того же содержимого не создаёт строку и не публикует событие
+    async fn store(&self, source: &str, payload: Value) -> Result<RunOutput, ApiError> {
+        match CacheRepo::create(&self.pool, source, payload).await? {
+            CacheWrite::New(id) => {
+                self.events.publish(ChangeEvent::new(
+                    space_topic(source),
+                    Some(id),
+                    serde_json::json!({ "source": source }),
+                )).await;
+                Ok(RunOutput { written: 1, unchanged: 0 })
+            }
+            CacheWrite::Unchanged(_) => Ok(RunOutput { written: 0, unchanged: 1 }),
+        }
     }
     
     pub async fn get_latest(&self, source: &str) -> Result<Option<crate::domain::SpaceCache>, ApiError> {
         Ok(CacheRepo::find_latest(&self.pool, source).await?)
     }
+    
+    pub async fn get_snapshot(
+        &self,
+        source: &str,
+        id: i64,
+        fields: &[Vec<String>],
+    ) -> Result<crate::domain::SpaceCache, ApiError> {
+        let mut cache = CacheRepo::find_by_id(&self.pool, source, id)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        if !fields.is_empty() {
+            cache.payload = project_fields(&cache.payload, fields);
+        }
+        Ok(cache)
+    }
+    
+    // Лишняя строка в запросе показывает, есть ли следующая страница
+    pub async fn history(
+        &self,
+        source: &str,
+        range: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+        after: Option<(DateTime<Utc>, i64)>,
+        limit: i64,
+        fields: &[Vec<String>],
+    ) -> Result<crate::domain::SpaceCachePage, ApiError> {
+        let mut items = CacheRepo::find_page(&self.pool, source, range, after, limit + 1).await?;
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|c| format!("{}_{}", c.fetched_at.timestamp_micros(), c.id))
+        } else {
+            None
+        };
+        
+        if !fields.is_empty() {
+            for item in &mut items {
+                item.payload = project_fields(&item.payload, fields);
+            }
+        }
+        
+        Ok(crate::domain::SpaceCachePage {
+            source: source.to_string(),
+            items,
+            next_cursor,
+        })
+    }
+}
+
+pub struct NeoService {
+    pool: PgPool,
+}
+
+impl NeoService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+    
+    pub async fn approaches(&self, query: &crate::domain::NeoQuery) -> Result<Vec<crate::domain::NeoApproach>, ApiError> {
+        Ok(NeoRepo::find_approaches(&self.pool, query).await?)
+    }
+}
+
+pub struct DonkiService {
+    pool: PgPool,
+}
+
+impl DonkiService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+    
+    pub async fn flares(&self, query: &crate::domain::FlareQuery) -> Result<Vec<crate::domain::Flare>, ApiError> {
+        Ok(DonkiRepo::find_flares(&self.pool, query).await?)
+    }
+    
+    // Вспышка и связанные с ней CME
+    pub async fn flare(&self, id: &str) -> Result<(crate::domain::Flare, Vec<crate::domain::Cme>), ApiError> {
+        let flare = DonkiRepo::find_flare(&self.pool, id).await?.ok_or(ApiError::NotFound)?;
+        let cmes = DonkiRepo::find_cmes_linked_to(&self.pool, id).await?;
+        Ok((flare, cmes))
+    }
+    
+    pub async fn cmes(&self, query: &crate::domain::CmeQuery) -> Result<Vec<crate::domain::Cme>, ApiError> {
+        Ok(DonkiRepo::find_cmes(&self.pool, query).await?)
+    }
+    
+    // CME и вспышки, с которыми он связан
+    pub async fn cme(&self, id: &str) -> Result<(crate::domain::Cme, Vec<crate::domain::Flare>), ApiError> {
+        let cme = DonkiRepo::find_cme(&self.pool, id).await?.ok_or(ApiError::NotFound)?;
+        let flares = DonkiRepo::find_flares_linked_to(&self.pool, id).await?;
+        Ok((cme, flares))
+    }
+}
+
+pub struct JobService {
+    catalog: Arc<Vec<JobInfo>>,
+    pool: PgPool,
+}
+
+impl JobService {
+    pub fn new(pool: PgPool, catalog: Arc<Vec<JobInfo>>) -> Self {
+        Self { catalog, pool }
+    }
+    
+    pub async fn statuses(&self) -> Result<Vec<crate::domain::JobStatus>, ApiError> {
+        let mut recorded = JobRunRepo::find_statuses(&self.pool).await?;
+        let now = Utc::now();
+        
+        let mut out = Vec::with_capacity(self.catalog.len());
+        for info in self.catalog.iter() {
+            let mut status = match recorded.iter().position(|s| s.name == info.name) {
+                Some(i) => recorded.remove(i),
+                None => crate::domain::JobStatus {
+                    name: info.name.to_string(),
+                    schedule: None,
+                    last_run: None,
+                    last_success_at: None,
+                    last_failure_at: None,
+                    stale: false,
+                },
+            };
+            
+            // Устаревшими считаем данные без успешного запуска за два периода
+            let max_age = chrono::Duration::from_std(info.schedule.period() * 2)
+                .unwrap_or_else(|_| chrono::Duration::zero());
+            status.stale = status.last_success_at.is_none_or(|t| now - t > max_age);
+            status.schedule = Some(info.schedule.to_string());
+            out.push(status);
+        }
+        
+        // Задачи, которых уже нет в планировщике, но есть в истории
+        out.extend(recorded);
+        Ok(out)
+    }
+    
+    pub async fn runs(&self, job_name: &str, limit: i64) -> Result<Vec<crate::domain::JobRun>, ApiError> {
+        let runs = JobRunRepo::find_runs(&self.pool, job_name, limit).await?;
+        if runs.is_empty() && !self.catalog.iter().any(|j| j.name == job_name) {
+            return Err(ApiError::NotFound);
+        }
+        Ok(runs)
+    }
+}
+
+const EXPORT_BATCH: i64 = 1000;
+const PURGE_BATCH: i64 = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+// Обслуживание БД для rust_iss-admin: статистика, выгрузка, очистка
+pub struct MaintenanceService {
+    pool: PgPool,
+}
+
+impl MaintenanceService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+    
+    pub async fn stats(&self) -> Result<crate::domain::DbStats, ApiError> {
+        Ok(crate::domain::DbStats {
+            schema_version: MaintenanceRepo::schema_version(&self.pool).await?,
+            tables: MaintenanceRepo::table_stats(&self.pool).await?,
+            space_sources: MaintenanceRepo::source_stats(&self.pool).await?,
+            watermarks: WatermarkRepo::find_all(&self.pool).await?,
+            jobs: JobRunRepo::find_statuses(&self.pool).await?,
+        })
+    }
+    
+    // Построчная выгрузка пачками, без загрузки всей таблицы в память.
+    // Колонки CSV берутся из первой строки, вложенный JSON пишется строкой
+    pub async fn export(
+        &self,
+        table: DataTable,
+        range: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+        source: Option<&str>,
+        format: ExportFormat,
+        out: &mut dyn std::io::Write,
+    ) -> Result<usize, ApiError> {
+        let io_err = |e: std::io::Error| ApiError::Internal(format!("write failed: {}", e));
+        let mut after_id = 0;
+        let mut written = 0;
+        let mut columns: Option<Vec<String>> = None;
+        
+        loop {
+            let batch = MaintenanceRepo::export_batch(&self.pool, table, after_id, range, source, EXPORT_BATCH).await?;
+            let done = (batch.len() as i64) < EXPORT_BATCH;
+            
+            for (id, row) in batch {
+                after_id = id;
+                match format {
+                    ExportFormat::Jsonl => writeln!(out, "{}", row).map_err(io_err)?,
+                    ExportFormat::Csv => {
+                        let object = row.as_object().cloned().unwrap_or_default();
+                        let columns = match &columns {
+                            Some(columns) => columns,
+                            None => {
+                                let header: Vec<String> = object.keys().cloned().collect();
+                                writeln!(out, "{}", header.iter().map(|c| csv_field(c)).collect::<Vec<_>>().join(","))
+                                    .map_err(io_err)?;
+                                columns.insert(header)
+                            }
+                        };
+                        let line: Vec<String> = columns.iter().map(|c| match object.get(c) {
+                            None | Some(Value::Null) => String::new(),
+                            Some(Value::String(v)) => csv_field(v),
+                            Some(v) => csv_field(&v.to_string()),
+                        }).collect();
+                        writeln!(out, "{}", line.join(",")).map_err(io_err)?;
+                    }
+                }
+                written += 1;
+            }
+            
+            if done {
+                break;
+            }
+        }
+        
+        out.flush().map_err(io_err)?;
+        Ok(written)
+    }
+    
+    // Удаляет строки старше cutoff пачками; dry_run только считает
+    pub async fn purge(
+        &self,
+        table: DataTable,
+        cutoff: DateTime<Utc>,
+        source: Option<&str>,
+        dry_run: bool,
+    ) -> Result<u64, ApiError> {
+        if dry_run {
+            return Ok(MaintenanceRepo::count_older(&self.pool, table, cutoff, source).await? as u64);
+        }
+        
+        let mut deleted = 0;
+        loop {
+            let n = MaintenanceRepo::delete_older_batch(&self.pool, table, cutoff, source, PURGE_BATCH).await?;
+            deleted += n;
+            if n < PURGE_BATCH as u64 {
+                break;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+// Сырые позиции МКС сворачиваются по суткам, каждые сутки - своя транзакция
+const RETENTION_WINDOW_DAYS: i64 = 1;
+const RETENTION_BATCH: i64 = 5000;
+// Пауза между пачками, чтобы очистка не занимала пул и не мешала запросам
+const RETENTION_PAUSE: std::time::Duration = std::time::Duration::from_millis(100);
+
+pub struct RetentionService {
+    pool: PgPool,
+    policy: RetentionPolicy,
+}
+
+impl RetentionService {
+    pub fn new(pool: PgPool, policy: RetentionPolicy) -> Self {
+        Self { pool, policy }
+    }
+    
+    // Применяет политику хранения; dry_run только считает и ничего не удаляет
+    pub async fn run(&self, dry_run: bool) -> Result<RetentionReport, ApiError> {
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        
+        if let Some(age) = self.policy.iss_raw {
+            let (aggregated, deleted) = self.compact_iss(now - age, dry_run).await?;
+            entries.push(RetentionEntry {
+                id: None,
+                ran_at: None,
+                target: "iss_fetch_log".to_string(),
+                source: None,
+                policy: format!("hourly aggregates after {}", format_age(age)),
+                aggregated,
+                deleted,
+            });
+        }
+        
+        if let Some(age) = self.policy.iss_hourly {
+            let cutoff = now - age;
+            let deleted = if dry_run {
+                RetentionRepo::count_hourly_older(&self.pool, cutoff).await?
+            } else {
+                self.delete_in_batches(|| {
+                    RetentionRepo::delete_hourly_older_batch(&self.pool, cutoff, RETENTION_BATCH)
+                })
+                .await?
+            };
+            entries.push(RetentionEntry {
+                id: None,
+                ran_at: None,
+                target: "iss_position_hourly".to_string(),
+                source: None,
+                policy: format!("older than {}", format_age(age)),
+                aggregated: 0,
+                deleted,
+            });
+        }
+        
+        for source in RetentionRepo::space_sources(&self.pool).await? {
+            let Some(keep) = self.policy.space_cache_keep(&source) else {
+                continue;
+            };
+            let deleted = if dry_run {
+                RetentionRepo::count_space_excess(&self.pool, &source, keep).await?
+            } else {
+                self.delete_in_batches(|| {
+                    RetentionRepo::delete_space_excess_batch(&self.pool, &source, keep, RETENTION_BATCH)
+                })
+                .await?
+            };
+            entries.push(RetentionEntry {
+                id: None,
+                ran_at: None,
+                target: "space_cache".to_string(),
+                source: Some(source),
+                policy: format!("keep last {}", keep),
+                aggregated: 0,
+                deleted,
+            });
+        }
+        
+        // В retention_log попадает только то, что действительно изменилось
+        if !dry_run {
+            for entry in entries.iter_mut().filter(|e| e.deleted > 0 || e.aggregated > 0) {
+                *entry = RetentionRepo::log(&self.pool, now, entry).await?;
+            }
+        }
+        
+        for entry in &entries {
+            tracing::info!(
+                "retention {}{}: {} ({} aggregated, {} deleted{})",
+                entry.target,
+                entry.source.as_ref().map(|s| format!("/{}", s)).unwrap_or_default(),
+                entry.policy,
+                entry.aggregated,
+                entry.deleted,
+                if dry_run { ", dry run" } else { "" },
+            );
+        }
+        
+        Ok(RetentionReport { dry_run, entries })
+    }
+    
+    pub async fn recent(&self, limit: i64) -> Result<Vec<RetentionEntry>, ApiError> {
+        Ok(RetentionRepo::find_recent(&self.pool, limit).await?)
+    }
+    
+    // Сворачивает только целые часы, чтобы час не оказался разрезан между запусками.
+    // Возвращает (записано часов, удалено сырых строк)
+    async fn compact_iss(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<(i64, i64), ApiError> {
+        let cutoff = truncate_to_hour(cutoff);
+        let Some(oldest) = RetentionRepo::oldest_raw_iss(&self.pool).await? else {
+            return Ok((0, 0));
+        };
+        if dry_run {
+            return Ok(RetentionRepo::count_raw_iss(&self.pool, oldest, cutoff).await?);
+        }
+        
+        let (mut aggregated, mut deleted) = (0, 0);
+        let mut from = truncate_to_hour(oldest);
+        while from < cutoff {
+            let to = (from + chrono::Duration::days(RETENTION_WINDOW_DAYS)).min(cutoff);
+            let (hours, rows) = RetentionRepo::compact_iss_window(&self.pool, from, to).await?;
+            aggregated += hours as i64;
+            deleted += rows as i64;
+            from = to;
+            tokio::time::sleep(RETENTION_PAUSE).await;
+        }
+        Ok((aggregated, deleted))
+    }
+    
+    async fn delete_in_batches<F, Fut>(&self, mut delete_batch: F) -> Result<i64, ApiError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<u64, sqlx::Error>>,
+    {
+        let mut deleted = 0;
+        loop {
+            let n = delete_batch().await?;
+            deleted += n as i64;
+            if n < RETENTION_BATCH as u64 {
+                return Ok(deleted);
+            }
+            tokio::time::sleep(RETENTION_PAUSE).await;
+        }
+    }
 }
 
 // Helper functions
+fn truncate_to_hour(at: DateTime<Utc>) -> DateTime<Utc> {
+    let seconds = at.timestamp();
+    Utc.timestamp_opt(seconds - seconds.rem_euclid(3600), 0).single().unwrap_or(at)
+}
+
+fn format_age(age: chrono::Duration) -> String {
+    let seconds = age.num_seconds();
+    match seconds {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+// Оставляет в payload только указанные пути, сохраняя вложенность.
+// Массив (например, список CME) проецируется поэлементно
+fn project_fields(payload: &Value, fields: &[Vec<String>]) -> Value {
+    if let Value::Array(items) = payload {
+        return Value::Array(items.iter().map(|item| project_fields(item, fields)).collect());
+    }
+    
+    let mut out = Value::Object(serde_json::Map::new());
+    'paths: for path in fields {
+        let (Some(value), Some((last, parents))) = (
+            path.iter().try_fold(payload, |v, key| v.get(key)),
+            path.split_last(),
+        ) else {
+            continue;
+        };
+        let mut target = &mut out;
+        for key in parents {
+            let Value::Object(map) = target else {
+                continue 'paths;
+            };
+            target = map.entry(key.clone()).or_insert_with(|| Value::Object(serde_json::Map::new()));
+        }
+        if let Value::Object(map) = target {
+            map.insert(last.clone(), value.clone());
+        }
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 fn extract_string(v: &Value, keys: &[&str]) -> Option<String> {
     for k in keys {
         if let Some(x) = v.get(*k) {
@@ -221,22 +1182,130 @@ fn extract_number(v: &Value) -> Option<f64> {
     None
 }
 
-fn extract_datetime(v: &Value, keys: &[&str]) -> Option<DateTime<Utc>> {
-    for k in keys {
-        if let Some(x) = v.get(*k) {
-            if let Some(s) = x.as_str() {
-                if let Ok(dt) = s.parse::<DateTime<Utc>>() {
-                    return Some(dt);
-                }
-                if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
-                    return Some(Utc.from_utc_datetime(&ndt));
-                }
-            } else if let Some(n) = x.as_i64() {
-                return Some(Utc.timestamp_opt(n, 0).single().unwrap_or_else(Utc::now));
-            }
-        }
-    }
-    None
+fn parse_iss_position(v: &Value) -> Option<crate::domain::IssPosition> {
+    Some(crate::domain::IssPosition {
+        latitude: extract_number(&v["latitude"])?,
+        longitude: extract_number(&v["longitude"])?,
+        altitude: extract_number(&v["altitude"]),
+        velocity: extract_number(&v["velocity"]),
+        visibility: v["visibility"].as_str().map(|s| s.to_string()),
+        footprint: extract_number(&v["footprint"]),
+        timestamp: v["timestamp"].as_i64().and_then(|t| Utc.timestamp_opt(t, 0).single()),
+    })
+}
+
+// Лента NeoWs: {"near_earth_objects": {"2024-01-01": [{...}, ...], ...}}.
+// Объект без id или имени пропускается, сближение без момента - тоже
+fn parse_neo_feed(feed: &Value) -> Vec<crate::domain::NeoObject> {
+    let Some(days) = feed.get("near_earth_objects").and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    
+    days.values()
+        .filter_map(|day| day.as_array())
+        .flatten()
+        .filter_map(|neo| {
+            let diameter = &neo["estimated_diameter"]["kilometers"];
+            let approaches = neo["close_approach_data"]
+                .as_array()
+                .map(|list| list.iter().filter_map(parse_close_approach).collect())
+                .unwrap_or_default();
+            Some(crate::domain::NeoObject {
+                id: extract_string(neo, &["id", "neo_reference_id"])?,
+                name: extract_string(neo, &["name"])?,
+                nasa_jpl_url: extract_string(neo, &["nasa_jpl_url"]),
+                absolute_magnitude: extract_number(&neo["absolute_magnitude_h"]),
+                diameter_min_km: extract_number(&diameter["estimated_diameter_min"]),
+                diameter_max_km: extract_number(&diameter["estimated_diameter_max"]),
+                hazardous: neo["is_potentially_hazardous_asteroid"].as_bool().unwrap_or(false),
+                sentry: neo["is_sentry_object"].as_bool().unwrap_or(false),
+                approaches,
+            })
+        })
+        .collect()
+}
+
+fn parse_close_approach(v: &Value) -> Option<crate::domain::NeoCloseApproach> {
+    // epoch_date_close_approach - миллисекунды; иначе "2024-Jan-01 05:43" или просто дата
+    let approach_at = v["epoch_date_close_approach"]
+        .as_i64()
+        .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+        .or_else(|| {
+            let full = v["close_approach_date_full"].as_str()?;
+            NaiveDateTime::parse_from_str(full, "%Y-%b-%d %H:%M").ok().map(|t| t.and_utc())
+        })
+        .or_else(|| {
+            let date = v["close_approach_date"].as_str()?;
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0).map(|t| t.and_utc())
+        })?;
+    
+    Some(crate::domain::NeoCloseApproach {
+        approach_at,
+        miss_distance_km: extract_number(&v["miss_distance"]["kilometers"]),
+        relative_velocity_kms: extract_number(&v["relative_velocity"]["kilometers_per_second"]),
+        orbiting_body: v["orbiting_body"].as_str().map(|s| s.to_string()),
+    })
+}
+
+// Время DONKI: "2024-01-01T12:00Z" (без секунд) или полный RFC 3339
+fn parse_donki_time(v: &Value) -> Option<DateTime<Utc>> {
+    let s = v.as_str()?;
+    s.parse::<DateTime<Utc>>()
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%MZ").ok().map(|t| t.and_utc()))
+}
+
+fn parse_linked_ids(v: &Value) -> Vec<String> {
+    v["linkedEvents"]
+        .as_array()
+        .map(|events| {
+            events
+                .iter()
+                .filter_map(|e| e["activityID"].as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_flare(v: &Value) -> Option<crate::domain::Flare> {
+    let class_type = v["classType"].as_str().map(|s| s.to_string());
+    Some(crate::domain::Flare {
+        id: extract_string(v, &["flrID", "activityID"])?,
+        class_flux: class_type.as_deref().and_then(crate::domain::Flare::flux_for_class),
+        class_type,
+        begin_at: parse_donki_time(&v["beginTime"]),
+        peak_at: parse_donki_time(&v["peakTime"]),
+        end_at: parse_donki_time(&v["endTime"]),
+        source_location: extract_string(v, &["sourceLocation"]),
+        active_region: v["activeRegionNum"].as_i64().and_then(|n| i32::try_from(n).ok()),
+        link: extract_string(v, &["link"]),
+        linked_ids: parse_linked_ids(v),
+    })
+}
+
+// Параметры CME - из анализа с isMostAccurate, иначе из последнего
+fn parse_cme(v: &Value) -> Option<crate::domain::Cme> {
+    let analyses = v["cmeAnalyses"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let analysis = analyses
+        .iter()
+        .find(|a| a["isMostAccurate"].as_bool() == Some(true))
+        .or(analyses.last())
+        .unwrap_or(&Value::Null);
+    
+    Some(crate::domain::Cme {
+        id: extract_string(v, &["activityID"])?,
+        start_at: parse_donki_time(&v["startTime"]),
+        speed_kms: extract_number(&analysis["speed"]),
+        half_angle_deg: extract_number(&analysis["halfAngle"]),
+        latitude: extract_number(&analysis["latitude"]),
+        longitude: extract_number(&analysis["longitude"]),
+        cme_type: analysis["type"].as_str().map(|s| s.to_string()),
+        source_location: extract_string(v, &["sourceLocation"]),
+        active_region: v["activeRegionNum"].as_i64().and_then(|n| i32::try_from(n).ok()),
+        note: extract_string(v, &["note"]),
+        link: extract_string(v, &["link"]),
+        linked_ids: parse_linked_ids(v),
+    })
 }
 
 fn extract_array(json: &Value) -> Vec<Value> {
@@ -261,9 +1330,353 @@ fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     6371.0 * c
 }
 
-fn last_days(n: i64) -> (String, String) {
-    let to = Utc::now().date_naive();
-    let from = to - Days::new(n as u64);
-    (from.to_string(), to.to_string())
+// Largest-Triangle-Three-Buckets: x - время, y - широта.
+// Широта ISS меняется плавно (синусоида), поэтому форма трека сохраняется
+fn lttb(points: Vec<crate::domain::IssTrackPoint>, threshold: usize) -> Vec<crate::domain::IssTrackPoint> {
+    let n = points.len();
+    if threshold >= n || threshold < 3 {
+        return points;
+    }
+    
+    let x = |i: usize| points[i].at.timestamp_millis() as f64;
+    let y = |i: usize| points[i].latitude;
+    
+    let bucket = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut selected = Vec::with_capacity(threshold);
+    selected.push(0);
+    let mut a = 0;
+    
+    for i in 0..threshold - 2 {
+        // Среднее следующего бакета - третья вершина треугольника
+        let next_start = ((i + 1) as f64 * bucket) as usize + 1;
+        let next_end = (((i + 2) as f64 * bucket) as usize + 1).min(n);
+        let next_len = (next_end - next_start) as f64;
+        let avg_x = (next_start..next_end).map(x).sum::<f64>() / next_len;
+        let avg_y = (next_start..next_end).map(y).sum::<f64>() / next_len;
+        
+        let start = (i as f64 * bucket) as usize + 1;
+        let end = ((i + 1) as f64 * bucket) as usize + 1;
+        
+        let mut best = start;
+        let mut best_area = -1.0;
+        for j in start..end {
+            let area = ((x(a) - avg_x) * (y(j) - y(a)) - (x(a) - x(j)) * (avg_y - y(a))).abs();
+            if area > best_area {
+                best_area = area;
+                best = j;
+            }
+        }
+        
+        selected.push(best);
+        a = best;
+    }
+    
+    selected.push(n - 1);
+    
+    let mut keep = selected.into_iter().peekable();
+    points.into_iter().enumerate().filter_map(|(i, p)| {
+        if keep.peek() == Some(&i) {
+            keep.next();
+            Some(p)
+        } else {
+            None
+        }
+    }).collect()
+}
+
+// Начальный азимут движения из точки 1 в точку 2, градусы от севера [0, 360)
+fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let rlat1 = lat1.to_radians();
+    let rlat2 = lat2.to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let y = dlon.sin() * rlat2.cos();
+    let x = rlat1.cos() * rlat2.sin() - rlat1.sin() * rlat2.cos() * dlon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+// Разбивает отрезок дат (включительно) на окна не длиннее FEED_WINDOW_DAYS
+fn date_windows(from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut windows = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = (start + Days::new(FEED_WINDOW_DAYS - 1)).min(to);
+        windows.push((start, end));
+        start = end + Days::new(1);
+    }
+    windows
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::IssTrackPoint;
+
+    fn track(latitudes: &[f64]) -> Vec<IssTrackPoint> {
+        let start = Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap();
+        latitudes
+            .iter()
+            .enumerate()
+            .map(|(i, &latitude)| IssTrackPoint {
+                id: i as i64,
+                at: start + chrono::Duration::seconds(60 * i as i64),
+                latitude,
+                longitude: 0.0,
+                altitude: None,
+                velocity: None,
+            })
+            .collect()
+    }
+
+    fn ids(points: &[IssTrackPoint]) -> Vec<i64> {
+        points.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn lttb_thresholds() {
+        // (точек, порог, ожидаемо точек)
+        let cases = [
+            (0, 5, 0),
+            (1, 3, 1),
+            (2, 3, 2),
+            (10, 0, 10),
+            (10, 1, 10),
+            (10, 2, 10),
+            (10, 3, 3),
+            (10, 9, 9),
+            (10, 10, 10),
+            (10, 50, 10),
+            (100, 10, 10),
+            (1000, 37, 37),
+        ];
+        for (len, threshold, expected) in cases {
+            let latitudes: Vec<f64> = (0..len).map(|i| (i as f64 / 7.0).sin() * 51.6).collect();
+            let points = track(&latitudes);
+            let result = lttb(points.clone(), threshold);
+            let case = format!("len {} threshold {}", len, threshold);
+            assert_eq!(result.len(), expected, "{}", case);
+            if expected == len {
+                assert_eq!(ids(&result), ids(&points), "{}", case);
+            }
+            if len > 0 {
+                assert_eq!(result.first().map(|p| p.id), Some(0), "{}", case);
+                assert_eq!(result.last().map(|p| p.id), Some(len as i64 - 1), "{}", case);
+            }
+            assert!(ids(&result).windows(2).all(|w| w[0] < w[1]), "{}", case);
+        }
+    }
+
+    #[test]
+    fn lttb_keeps_spike() {
+        for (len, spike, threshold) in [(100, 50, 10), (100, 1, 5), (100, 98, 5), (500, 333, 20)] {
+            let mut latitudes = vec![0.0; len];
+            latitudes[spike] = 40.0;
+            let result = lttb(track(&latitudes), threshold);
+            assert!(
+                ids(&result).contains(&(spike as i64)),
+                "len {} spike {} threshold {}: {:?}",
+                len,
+                spike,
+                threshold,
+                ids(&result)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_neo_feed_fixture() {
+        let feed: Value = serde_json::from_str(include_str!("../fixtures/upstream/neo_feed.json")).unwrap();
+        let neos = parse_neo_feed(&feed);
+        assert_eq!(neos.len(), 3);
+
+        let neo = neos.iter().find(|n| n.id == "2465633").unwrap();
+        assert_eq!(neo.name, "465633 (2009 JR5)");
+        assert_eq!(neo.absolute_magnitude, Some(20.36));
+        assert_eq!(neo.diameter_min_km, Some(0.2251930467));
+        assert_eq!(neo.diameter_max_km, Some(0.5035469604));
+        assert!(neo.hazardous);
+        assert!(!neo.sentry);
+        assert_eq!(neo.approaches.len(), 1);
+        // В ленте числа сближения - строки
+        let approach = &neo.approaches[0];
+        assert_eq!(approach.approach_at, Utc.with_ymd_and_hms(2024, 5, 10, 20, 34, 0).unwrap());
+        assert_eq!(approach.miss_distance_km, Some(45290438.20445262));
+        assert_eq!(approach.relative_velocity_kms, Some(18.1279547773));
+        assert_eq!(approach.orbiting_body.as_deref(), Some("Earth"));
+
+        let hazardous: Vec<&str> = neos.iter().filter(|n| n.hazardous).map(|n| n.id.as_str()).collect();
+        assert_eq!(hazardous, ["2465633"]);
+        assert!(neos.iter().all(|n| n.approaches.len() == 1));
+    }
+
+    #[test]
+    fn parse_neo_feed_loose_fields() {
+        let feed = serde_json::json!({"near_earth_objects": {"2024-05-10": [
+            {"neo_reference_id": 101, "name": "numeric id", "absolute_magnitude_h": "22.5",
+             "estimated_diameter": {"kilometers": {"estimated_diameter_min": "0.1"}},
+             "close_approach_data": [
+                {"epoch_date_close_approach": 1715373240000_i64,
+                 "miss_distance": {"kilometers": 1234.5}, "relative_velocity": {"kilometers_per_second": "fast"}},
+                {"close_approach_date_full": "2024-May-11 03:02"},
+                {"close_approach_date": "2024-05-12"},
+                {"close_approach_date": "not a date"},
+                {"miss_distance": {"kilometers": "1"}}
+             ]},
+            {"id": "102", "name": "bare"},
+            {"id": "103"},
+            {"name": "no id"},
+            "junk"
+        ]}});
+        let neos = parse_neo_feed(&feed);
+        let ids: Vec<&str> = neos.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["101", "102"]);
+
+        let loose = &neos[0];
+        assert_eq!(loose.absolute_magnitude, Some(22.5));
+        assert_eq!(loose.diameter_min_km, Some(0.1));
+        assert_eq!(loose.diameter_max_km, None);
+        assert!(!loose.hazardous && !loose.sentry);
+        // Сближение без распознаваемого момента отбрасывается
+        let times: Vec<DateTime<Utc>> = loose.approaches.iter().map(|a| a.approach_at).collect();
+        assert_eq!(
+            times,
+            [
+                Utc.with_ymd_and_hms(2024, 5, 10, 20, 34, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 11, 3, 2, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 12, 0, 0, 0).unwrap(),
+            ]
+        );
+        assert_eq!(loose.approaches[0].miss_distance_km, Some(1234.5));
+        assert_eq!(loose.approaches[0].relative_velocity_kms, None);
+
+        let bare = &neos[1];
+        assert_eq!((bare.absolute_magnitude, bare.diameter_min_km), (None, None));
+        assert!(bare.approaches.is_empty());
+
+        assert!(parse_neo_feed(&serde_json::json!({})).is_empty());
+        assert!(parse_neo_feed(&serde_json::json!({"near_earth_objects": []})).is_empty());
+    }
+
+    #[test]
+    fn parse_flare_fixture() {
+        let items: Value = serde_json::from_str(include_str!("../fixtures/upstream/donki_flr.json")).unwrap();
+        let flares: Vec<_> = items.as_array().unwrap().iter().filter_map(parse_flare).collect();
+        assert_eq!(flares.len(), 3);
+
+        let flare = &flares[0];
+        assert_eq!(flare.id, "2024-05-10T06:27:00-FLR-001");
+        assert_eq!(flare.class_type.as_deref(), Some("X3.9"));
+        assert!(flare.class_flux.is_some_and(|f| (f - 3.9e-4).abs() < 1e-12));
+        assert_eq!(flare.begin_at, Some(Utc.with_ymd_and_hms(2024, 5, 10, 6, 27, 0).unwrap()));
+        assert_eq!(flare.peak_at, Some(Utc.with_ymd_and_hms(2024, 5, 10, 6, 54, 0).unwrap()));
+        assert_eq!(flare.end_at, Some(Utc.with_ymd_and_hms(2024, 5, 10, 7, 6, 0).unwrap()));
+        assert_eq!(flare.source_location.as_deref(), Some("S17W29"));
+        assert_eq!(flare.active_region, Some(13664));
+        assert_eq!(flare.linked_ids, ["2024-05-10T07:12:00-CME-001"]);
+        assert!(flares[1].linked_ids.is_empty());
+
+        let classes: Vec<_> = flares.iter().filter_map(|f| f.class_type.as_deref()).collect();
+        assert_eq!(classes, ["X3.9", "X5.8", "M8.8"]);
+    }
+
+    #[test]
+    fn parse_flare_loose_fields() {
+        // Без flrID берётся activityID; без обоих запись пропускается
+        let flare = parse_flare(&serde_json::json!({
+            "activityID": "A-1", "classType": "bogus", "beginTime": "2024-05-10T06:27:30Z",
+            "peakTime": "yesterday", "activeRegionNum": 99999999999_i64
+        }))
+        .unwrap();
+        assert_eq!(flare.id, "A-1");
+        assert_eq!((flare.class_type.as_deref(), flare.class_flux), (Some("bogus"), None));
+        assert_eq!(flare.begin_at, Some(Utc.with_ymd_and_hms(2024, 5, 10, 6, 27, 30).unwrap()));
+        assert_eq!((flare.peak_at, flare.end_at, flare.active_region), (None, None, None));
+        assert!(parse_flare(&serde_json::json!({"classType": "X1"})).is_none());
+    }
+
+    #[test]
+    fn parse_cme_fixture() {
+        let items: Value = serde_json::from_str(include_str!("../fixtures/upstream/donki_cme.json")).unwrap();
+        let cmes: Vec<_> = items.as_array().unwrap().iter().filter_map(parse_cme).collect();
+        assert_eq!(cmes.len(), 2);
+
+        // Параметры из анализа с isMostAccurate, а не из первого
+        let cme = &cmes[0];
+        assert_eq!(cme.id, "2024-05-10T07:12:00-CME-001");
+        assert_eq!(cme.start_at, Some(Utc.with_ymd_and_hms(2024, 5, 10, 7, 12, 0).unwrap()));
+        assert_eq!((cme.speed_kms, cme.half_angle_deg), (Some(1205.0), Some(52.0)));
+        assert_eq!((cme.latitude, cme.longitude), (Some(-15.0), Some(21.0)));
+        assert_eq!(cme.cme_type.as_deref(), Some("R"));
+        assert!(cme.note.as_deref().is_some_and(|n| n.contains("X3.9")));
+        assert_eq!(cme.linked_ids, ["2024-05-10T06:27:00-FLR-001", "2024-05-11T09:00:00-GST-001"]);
+
+        let cme = &cmes[1];
+        assert_eq!((cme.speed_kms, cme.cme_type.as_deref()), (Some(880.0), Some("C")));
+        // Пустая заметка - не заметка
+        assert_eq!(cme.note, None);
+        assert!(cme.linked_ids.is_empty());
+    }
+
+    #[test]
+    fn parse_cme_analysis_choice() {
+        let cases = [
+            (serde_json::json!([]), None),
+            (serde_json::json!(null), None),
+            (serde_json::json!([{"speed": 500.0}, {"speed": "700"}]), Some(700.0)),
+            (serde_json::json!([{"speed": 500.0, "isMostAccurate": true}, {"speed": 700.0}]), Some(500.0)),
+            (serde_json::json!([{"speed": 500.0, "isMostAccurate": false}, {"speed": null}]), None),
+        ];
+        for (analyses, speed) in cases {
+            let cme = parse_cme(&serde_json::json!({"activityID": "C-1", "cmeAnalyses": analyses})).unwrap();
+            assert_eq!(cme.speed_kms, speed, "{}", analyses);
+        }
+        assert!(parse_cme(&serde_json::json!({"cmeAnalyses": []})).is_none());
+    }
+
+    #[test]
+    fn parse_linked_events() {
+        let cases: [(Value, &[&str]); 6] = [
+            (serde_json::json!({}), &[]),
+            (serde_json::json!({"linkedEvents": null}), &[]),
+            (serde_json::json!({"linkedEvents": []}), &[]),
+            (serde_json::json!({"linkedEvents": "2024-05-10T07:12:00-CME-001"}), &[]),
+            (
+                serde_json::json!({"linkedEvents": [{"activityID": "A"}, {"activityID": "B"}]}),
+                &["A", "B"],
+            ),
+            (
+                serde_json::json!({"linkedEvents": [{"activityID": 7}, {}, "C", {"activityID": "D"}]}),
+                &["D"],
+            ),
+        ];
+        for (event, expected) in cases {
+            assert_eq!(parse_linked_ids(&event), expected, "{}", event);
+        }
+    }
+
+    #[test]
+    fn date_windows_split() {
+        let d = |day: u32| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+        // (с, по, окна) - дни мая 2024
+        let cases = [
+            // Один день: начало и конец включительно
+            (10, 10, vec![(10, 10)]),
+            (10, 12, vec![(10, 12)]),
+            (1, 7, vec![(1, 7)]),
+            (1, 8, vec![(1, 7), (8, 8)]),
+            (1, 14, vec![(1, 7), (8, 14)]),
+            (1, 31, vec![(1, 7), (8, 14), (15, 21), (22, 28), (29, 31)]),
+            (12, 10, vec![]),
+        ];
+        for (from, to, expected) in cases {
+            let expected: Vec<_> = expected.into_iter().map(|(a, b)| (d(a), d(b))).collect();
+            assert_eq!(date_windows(d(from), d(to)), expected, "{}..{}", from, to);
+        }
+
+        // Через границу месяца
+        let windows = date_windows(d(28), NaiveDate::from_ymd_opt(2024, 6, 5).unwrap());
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0], (d(28), NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()));
+        assert_eq!(windows[1].0, NaiveDate::from_ymd_opt(2024, 6, 4).unwrap());
+    }
+}
@@ -1,8 +1,11 @@
 use sqlx::PgPool;
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
-use crate::clients::{IssClient, NasaClient, SpaceXClient};
+use crate::clients::{IssClient, NasaClient, SpaceXClient, TleClient};
 use crate::config::Config;
+use crate::events::EventBus;
+use crate::scheduler::JobInfo;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -10,17 +13,33 @@ pub struct AppState {
     pub iss_client: Arc<IssClient>,
     pub nasa_client: Arc<NasaClient>,
     pub spacex_client: Arc<SpaceXClient>,
+    pub tle_client: Arc<TleClient>,
     pub config: Config,
+    pub jobs: Arc<Vec<JobInfo>>,
+    // Уведомления о новых данных для /iss/stream и /ws
+    pub events: EventBus,
+    // Отменяется при остановке сервиса, чтобы закрыть долгие соединения
+    pub shutdown: CancellationToken,
 }
 
 impl AppState {
-    pub fn new(pool: PgPool, config: Config) -> Result<Self, crate::domain::ApiError> {
+    pub fn new(
+        pool: PgPool,
+        config: Config,
+        jobs: Vec<JobInfo>,
+        shutdown: CancellationToken,
+    ) -> Result<Self, crate::domain::ApiError> {
+        let events = EventBus::new(pool.clone(), config.event_bus);
         Ok(Self {
             pool,
             iss_client: Arc::new(IssClient::new(config.clone())?),
             nasa_client: Arc::new(NasaClient::new(config.clone())?),
             spacex_client: Arc::new(SpaceXClient::new(config.clone())?),
+            tle_client: Arc::new(TleClient::new(config.clone())?),
             config,
+            jobs: Arc::new(jobs),
+            events,
+            shutdown,
         })
     }
 }
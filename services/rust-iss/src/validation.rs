@@ -1,12 +1,20 @@
-use serde_json::Value;
-use crate::domain::ApiError;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::domain::{
+    ApiError, CmeQuery, Flare, FlareQuery, NeoQuery, NeoSort, OsdrCursor, OsdrSearchQuery, OsdrSort,
+};
+use crate::events::TOPICS;
+use crate::mapping::OsdrMapping;
+use crate::orbit::Observer;
 
 pub struct Validator;
 
 impl Validator {
     pub fn validate_limit(limit: Option<i64>) -> Result<i64, ApiError> {
         let limit = limit.unwrap_or(20);
-        if limit < 1 || limit > 1000 {
+        if !(1..=1000).contains(&limit) {
             return Err(ApiError::Validation(
                 "limit must be between 1 and 1000".to_string()
             ));
@@ -24,13 +32,495 @@ impl Validator {
         Ok(())
     }
     
-    pub fn validate_json_payload(payload: &Value) -> Result<(), ApiError> {
-        if !payload.is_object() && !payload.is_array() {
+    // Источник и даты YYYY-MM-DD для догрузки; диапазон включительный
+    pub fn validate_backfill(source: &str, from: &str, to: &str) -> Result<(NaiveDate, NaiveDate), ApiError> {
+        let allowed = ["neo", "flr", "cme"];
+        if !allowed.contains(&source) {
             return Err(ApiError::Validation(
-                "payload must be a JSON object or array".to_string()
+                format!("backfill source must be one of: {}", allowed.join(", "))
             ));
         }
-        Ok(())
+        
+        let parse = |value: &str, name: &str| {
+            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                .map_err(|_| ApiError::Validation(format!("{} must be a date like 2024-01-31", name)))
+        };
+        let (from, to) = (parse(from, "from")?, parse(to, "to")?);
+        if from > to {
+            return Err(ApiError::Validation("from must not be later than to".to_string()));
+        }
+        Ok((from, to))
+    }
+    
+    pub fn validate_time(value: Option<&String>, name: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+        match value {
+            Some(v) => v.parse::<DateTime<Utc>>()
+                .map(Some)
+                .map_err(|_| ApiError::Validation(format!("{} must be an RFC 3339 timestamp", name))),
+            None => Ok(None),
+        }
+    }
+    
+    // По умолчанию - последние сутки до `to` (или до текущего момента)
+    pub fn validate_time_range(
+        from: Option<&String>,
+        to: Option<&String>,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>), ApiError> {
+        let to = Self::validate_time(to, "to")?.unwrap_or_else(Utc::now);
+        let from = Self::validate_time(from, "from")?.unwrap_or(to - Duration::days(1));
+        if from >= to {
+            return Err(ApiError::Validation("from must be earlier than to".to_string()));
+        }
+        Ok((from, to))
+    }
+    
+    pub fn validate_max_points(value: Option<&String>) -> Result<usize, ApiError> {
+        let max_points = match value {
+            Some(v) => v.parse::<usize>()
+                .map_err(|_| ApiError::Validation("invalid max_points parameter".to_string()))?,
+            None => 1000,
+        };
+        if !(3..=10000).contains(&max_points) {
+            return Err(ApiError::Validation(
+                "max_points must be between 3 and 10000".to_string()
+            ));
+        }
+        Ok(max_points)
+    }
+    
+    pub fn validate_last_event_id(value: Option<&str>) -> Result<Option<i64>, ApiError> {
+        match value {
+            Some(v) => v.trim().parse::<i64>()
+                .ok()
+                .filter(|id| *id >= 0)
+                .map(Some)
+                .ok_or_else(|| ApiError::Validation("Last-Event-ID must be a non-negative integer".to_string())),
+            None => Ok(None),
+        }
+    }
+    
+    // Курсор истории space_cache: "<fetched_at в микросекундах>_<id>"
+    pub fn validate_cursor(value: Option<&String>) -> Result<Option<(DateTime<Utc>, i64)>, ApiError> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        value
+            .split_once('_')
+            .and_then(|(micros, id)| {
+                let at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+                Some((at, id.parse().ok()?))
+            })
+            .map(Some)
+            .ok_or_else(|| ApiError::Validation("invalid cursor".to_string()))
+    }
+    
+    // fields=title,links.webcast - пути внутри payload через точку, не больше 20
+    pub fn validate_fields(value: Option<&String>) -> Result<Vec<Vec<String>>, ApiError> {
+        let Some(value) = value else {
+            return Ok(Vec::new());
+        };
+        let fields: Vec<Vec<String>> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(|f| f.split('.').map(str::to_string).collect())
+            .collect();
+        
+        let valid_segment = |s: &String| {
+            !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        };
+        if fields.len() > 20 || fields.iter().flatten().any(|s| !valid_segment(s)) {
+            return Err(ApiError::Validation(
+                "fields must be up to 20 comma-separated paths like title or links.webcast".to_string()
+            ));
+        }
+        Ok(fields)
+    }
+    
+    // По умолчанию - ближайшие 7 суток от начала текущего дня, сортировка по дате сближения
+    pub fn validate_neo_query(params: &HashMap<String, String>) -> Result<NeoQuery, ApiError> {
+        let hazardous = match params.get("hazardous").map(|v| v.trim().to_lowercase()) {
+            None => None,
+            Some(v) if v == "true" || v == "1" => Some(true),
+            Some(v) if v == "false" || v == "0" => Some(false),
+            Some(_) => return Err(ApiError::Validation("hazardous must be true or false".to_string())),
+        };
+        
+        let from = Self::validate_time(params.get("from"), "from")?;
+        let to = Self::validate_time(params.get("to"), "to")?;
+        let from = from.unwrap_or_else(|| match to {
+            Some(to) => to - Duration::days(7),
+            None => Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        });
+        let to = to.unwrap_or(from + Duration::days(7));
+        if from >= to {
+            return Err(ApiError::Validation("from must be earlier than to".to_string()));
+        }
+        if to - from > Duration::days(366) {
+            return Err(ApiError::Validation("range must not exceed 366 days".to_string()));
+        }
+        
+        let max_miss_km = match params.get("max_miss_km") {
+            Some(v) => Some(v.parse::<f64>()
+                .ok()
+                .filter(|km| km.is_finite() && *km > 0.0)
+                .ok_or_else(|| ApiError::Validation("max_miss_km must be a positive number".to_string()))?),
+            None => None,
+        };
+        
+        let sort = match params.get("sort").map(String::as_str) {
+            None | Some("approach") => NeoSort::Approach,
+            Some("miss") => NeoSort::Miss,
+            Some("velocity") => NeoSort::Velocity,
+            Some("diameter") => NeoSort::Diameter,
+            Some(_) => return Err(ApiError::Validation(
+                "sort must be one of: approach, miss, velocity, diameter".to_string()
+            )),
+        };
+        
+        Ok(NeoQuery {
+            hazardous,
+            from,
+            to,
+            max_miss_km,
+            sort,
+            limit: Self::validate_limit_param(params)?,
+        })
+    }
+    
+    // ?from=&to=&min_class=M5&limit=, по умолчанию - последние 30 суток
+    pub fn validate_flare_query(params: &HashMap<String, String>) -> Result<FlareQuery, ApiError> {
+        let (from, to) = Self::validate_event_range(params)?;
+        let min_flux = match params.get("min_class") {
+            Some(class) => Some(Flare::flux_for_class(class).ok_or_else(|| {
+                ApiError::Validation("min_class must look like C, M5 or X1.2".to_string())
+            })?),
+            None => None,
+        };
+        Ok(FlareQuery {
+            from,
+            to,
+            min_flux,
+            limit: Self::validate_limit_param(params)?,
+        })
+    }
+    
+    // ?from=&to=&min_speed=&limit=, скорость в км/с
+    pub fn validate_cme_query(params: &HashMap<String, String>) -> Result<CmeQuery, ApiError> {
+        let (from, to) = Self::validate_event_range(params)?;
+        let min_speed = match params.get("min_speed") {
+            Some(v) => Some(v.parse::<f64>()
+                .ok()
+                .filter(|s| s.is_finite() && *s >= 0.0)
+                .ok_or_else(|| ApiError::Validation("min_speed must be a non-negative number".to_string()))?),
+            None => None,
+        };
+        Ok(CmeQuery {
+            from,
+            to,
+            min_speed,
+            limit: Self::validate_limit_param(params)?,
+        })
+    }
+    
+    // {"payload": датасет или массив датасетов, "profile": необязательный профиль}
+    pub fn validate_mapping_dry_run(
+        mut body: serde_json::Value,
+    ) -> Result<(Vec<serde_json::Value>, Option<OsdrMapping>), ApiError> {
+        let items = match body.get_mut("payload").map(serde_json::Value::take) {
+            Some(serde_json::Value::Array(items)) => items,
+            Some(item @ serde_json::Value::Object(_)) => vec![item],
+            _ => return Err(ApiError::Validation("payload must be an object or an array of objects".to_string())),
+        };
+        if items.is_empty() || items.len() > 100 {
+            return Err(ApiError::Validation("payload must contain between 1 and 100 items".to_string()));
+        }
+        let profile = match body.get_mut("profile").map(serde_json::Value::take) {
+            Some(profile) if !profile.is_null() => Some(
+                OsdrMapping::from_value(profile)
+                    .map_err(|e| ApiError::Validation(format!("invalid profile: {}", e)))?,
+            ),
+            _ => None,
+        };
+        Ok((items, profile))
+    }
+    
+    // q - в синтаксисе websearch_to_tsquery. Без sort с q сортируется по релевантности,
+    // без q - по updated_at; курсор должен быть от той же сортировки
+    pub fn validate_osdr_search(params: &HashMap<String, String>) -> Result<OsdrSearchQuery, ApiError> {
+        let text = |name: &str| {
+            params.get(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        };
+        let q = text("q");
+        if q.as_ref().is_some_and(|q| q.chars().count() > 200) {
+            return Err(ApiError::Validation("q must not exceed 200 characters".to_string()));
+        }
+        
+        let sort = match params.get("sort").map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") if q.is_some() => OsdrSort::Relevance,
+            None | Some("") => OsdrSort::Updated,
+            Some("relevance") if q.is_some() => OsdrSort::Relevance,
+            Some("relevance") => {
+                return Err(ApiError::Validation("sort=relevance requires q".to_string()));
+            }
+            Some("updated") => OsdrSort::Updated,
+            Some("inserted") => OsdrSort::Inserted,
+            Some(_) => {
+                return Err(ApiError::Validation(
+                    "sort must be one of: relevance, updated, inserted".to_string()
+                ));
+            }
+        };
+        
+        let after = match (sort, params.get("cursor")) {
+            (_, None) => None,
+            (OsdrSort::Relevance, Some(cursor)) => cursor
+                .split_once('_')
+                .and_then(|(rank, id)| {
+                    let rank = rank.parse::<f32>().ok().filter(|r| r.is_finite())?;
+                    Some(OsdrCursor::Rank(rank, id.parse().ok()?))
+                })
+                .map(Some)
+                .ok_or_else(|| ApiError::Validation("invalid cursor".to_string()))?,
+            (_, cursor) => Self::validate_cursor(cursor)?.map(|(at, id)| OsdrCursor::Time(at, id)),
+        };
+        
+        Ok(OsdrSearchQuery {
+            q,
+            status: text("status"),
+            updated_since: Self::validate_time(params.get("updated_since"), "updated_since")?,
+            sort,
+            after,
+            limit: Self::validate_limit_param(params)?,
+        })
+    }
+    
+    fn validate_event_range(params: &HashMap<String, String>) -> Result<(DateTime<Utc>, DateTime<Utc>), ApiError> {
+        let to = Self::validate_time(params.get("to"), "to")?.unwrap_or_else(Utc::now);
+        let from = Self::validate_time(params.get("from"), "from")?.unwrap_or(to - Duration::days(30));
+        if from >= to {
+            return Err(ApiError::Validation("from must be earlier than to".to_string()));
+        }
+        if to - from > Duration::days(366) {
+            return Err(ApiError::Validation("range must not exceed 366 days".to_string()));
+        }
+        Ok((from, to))
+    }
+    
+    pub fn validate_limit_param(params: &HashMap<String, String>) -> Result<i64, ApiError> {
+        let limit = match params.get("limit") {
+            Some(l) => Some(l.parse::<i64>()
+                .map_err(|_| ApiError::Validation("invalid limit parameter".to_string()))?),
+            None => None,
+        };
+        Self::validate_limit(limit)
+    }
+    
+    pub fn validate_topics(topics: &[String]) -> Result<Vec<String>, ApiError> {
+        topics
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                if TOPICS.contains(&t) {
+                    Ok(t.to_string())
+                } else {
+                    Err(ApiError::Validation(format!(
+                        "unknown topic '{}', expected one of: {}",
+                        t,
+                        TOPICS.join(", ")
+                    )))
+                }
+            })
+            .collect()
+    }
+    
+    // Окно вида 90s, 30m, 1h, 2d; не больше 7 суток
+    pub fn validate_window(value: &str) -> Result<Duration, ApiError> {
+        match parse_duration(value) {
+            Some(window) if window > Duration::zero() && window <= Duration::days(7) => Ok(window),
+            _ => Err(ApiError::Validation(
+                "window must look like 30m, 1h or 2d (max 7d)".to_string()
+            )),
+        }
+    }
+    
+    // По умолчанию - ближайшие 90 минут (примерно один виток) с шагом 60 секунд
+    pub fn validate_prediction_range(
+        from: Option<&String>,
+        to: Option<&String>,
+        step: Option<&String>,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>, Duration), ApiError> {
+        let from = Self::validate_time(from, "from")?.unwrap_or_else(Utc::now);
+        let to = Self::validate_time(to, "to")?.unwrap_or(from + Duration::minutes(90));
+        if from >= to || to - from > Duration::days(7) {
+            return Err(ApiError::Validation(
+                "from must be earlier than to, range at most 7d".to_string()
+            ));
+        }
+        
+        let step = match step {
+            Some(s) => parse_duration(s)
+                .filter(|d| *d >= Duration::seconds(1) && *d <= Duration::hours(1))
+                .ok_or_else(|| ApiError::Validation(
+                    "step must be between 1s and 1h, e.g. 30s or 5m".to_string()
+                ))?,
+            None => Duration::seconds(60),
+        };
+        if (to - from).num_seconds() / step.num_seconds() > 10000 {
+            return Err(ApiError::Validation("too many points, increase step".to_string()));
+        }
+        Ok((from, to, step))
+    }
+    
+    // lat/lon в градусах, alt - высота над уровнем моря в метрах
+    pub fn validate_observer(
+        lat: Option<&String>,
+        lon: Option<&String>,
+        alt: Option<&String>,
+    ) -> Result<Observer, ApiError> {
+        let coordinate = |value: Option<&String>, name: &str, limit: f64| {
+            value
+                .ok_or_else(|| ApiError::Validation(format!("{} is required", name)))?
+                .parse::<f64>()
+                .ok()
+                .filter(|v| (-limit..=limit).contains(v))
+                .ok_or_else(|| ApiError::Validation(
+                    format!("{} must be a number between -{} and {}", name, limit, limit)
+                ))
+        };
+        let latitude = coordinate(lat, "lat", 90.0)?;
+        let longitude = coordinate(lon, "lon", 180.0)?;
+        let altitude_m = match alt {
+            Some(v) => v.parse::<f64>()
+                .ok()
+                .filter(|a| (-500.0..=9000.0).contains(a))
+                .ok_or_else(|| ApiError::Validation(
+                    "alt must be in meters between -500 and 9000".to_string()
+                ))?,
+            None => 0.0,
+        };
+        Ok(Observer {
+            latitude,
+            longitude,
+            altitude_km: altitude_m / 1000.0,
+        })
+    }
+    
+    // days: 1..=10 (по умолчанию 3), min_elevation: 0..=90 градусов (по умолчанию 10)
+    pub fn validate_pass_window(
+        days: Option<&String>,
+        min_elevation: Option<&String>,
+    ) -> Result<(i64, f64), ApiError> {
+        let days = match days {
+            Some(v) => v.parse::<i64>()
+                .ok()
+                .filter(|d| (1..=10).contains(d))
+                .ok_or_else(|| ApiError::Validation("days must be between 1 and 10".to_string()))?,
+            None => 3,
+        };
+        let min_elevation = match min_elevation {
+            Some(v) => v.parse::<f64>()
+                .ok()
+                .filter(|e| (0.0..=90.0).contains(e))
+                .ok_or_else(|| ApiError::Validation(
+                    "min_elevation must be between 0 and 90 degrees".to_string()
+                ))?,
+            None => 10.0,
+        };
+        Ok((days, min_elevation))
     }
 }
 
+// Больше века сроки не нужны, а DateTime - Duration при огромных значениях паникует
+const MAX_DURATION_DAYS: i64 = 36_525;
+
+// 90 или 90s, 30m, 1h, 2d; None при переполнении
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let duration = match value.parse::<i64>() {
+        Ok(seconds) => Duration::try_seconds(seconds)?,
+        Err(_) => {
+            let split = value.char_indices().last()?.0;
+            let (amount, unit) = value.split_at(split);
+            let amount = amount.parse::<i64>().ok()?;
+            match unit {
+                "s" => Duration::try_seconds(amount)?,
+                "m" => Duration::try_minutes(amount)?,
+                "h" => Duration::try_hours(amount)?,
+                "d" => Duration::try_days(amount)?,
+                _ => return None,
+            }
+        }
+    };
+    (duration.num_days().abs() <= MAX_DURATION_DAYS).then_some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        let cases = [
+            ("90", Some(Duration::seconds(90))),
+            ("90s", Some(Duration::seconds(90))),
+            ("30m", Some(Duration::minutes(30))),
+            (" 1h ", Some(Duration::hours(1))),
+            ("2d", Some(Duration::days(2))),
+            ("0", Some(Duration::zero())),
+            ("-5m", Some(Duration::minutes(-5))),
+            ("", None),
+            ("h", None),
+            ("5w", None),
+            ("1.5h", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_duration(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        for input in [
+            "9223372036854775807",
+            "9223372036854775807s",
+            "9999999999999999d",
+            "-9999999999999999d",
+            "999999999999999h",
+            "999999999999999999m",
+            "36526d",
+        ] {
+            assert_eq!(parse_duration(input), None, "input {:?}", input);
+        }
+        assert_eq!(parse_duration("36525d"), Some(Duration::days(36_525)));
+    }
+
+    #[test]
+    fn huge_step_and_window_are_validation_errors() {
+        let step = "9223372036854775807".to_string();
+        assert!(Validator::validate_prediction_range(None, None, Some(&step)).is_err());
+        assert!(Validator::validate_window("9999999999999999d").is_err());
+    }
+
+    #[test]
+    fn limit_param() {
+        let cases = [
+            (None, Some(20)),
+            (Some("1"), Some(1)),
+            (Some("1000"), Some(1000)),
+            (Some("0"), None),
+            (Some("1001"), None),
+            (Some("-5"), None),
+            (Some("ten"), None),
+            (Some(""), None),
+        ];
+        for (limit, expected) in cases {
+            let params: HashMap<String, String> =
+                limit.map(|l| ("limit".to_string(), l.to_string())).into_iter().collect();
+            assert_eq!(Validator::validate_limit_param(&params).ok(), expected, "limit {:?}", limit);
+            let neo = Validator::validate_neo_query(&params);
+            assert_eq!(neo.ok().map(|q| q.limit), expected, "neo limit {:?}", limit);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::events::ChangeEvent;
+use crate::state::AppState;
+use crate::validation::Validator;
+
+// Сообщения клиента:
+//   {"action": "subscribe", "topics": ["iss", "space:neo"]}
+//   {"action": "unsubscribe", "topics": ["iss"]}
+//   {"action": "ping"}
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    Subscribe { topics: Vec<String> },
+    Unsubscribe { topics: Vec<String> },
+    Ping,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ServerMessage<'a> {
+    Subscribed { topics: &'a BTreeSet<String> },
+    Event(&'a ChangeEvent),
+    Lagged { skipped: u64 },
+    Pong,
+    Error { message: String },
+}
+
+pub async fn session(mut socket: WebSocket, state: AppState, mut topics: BTreeSet<String>) {
+    let mut events = state.events.subscribe();
+
+    if send(&mut socket, &ServerMessage::Subscribed { topics: &topics }).await.is_err() {
+        return;
+    }
+
+    loop {
+        let sent = tokio::select! {
+            _ = state.shutdown.cancelled() => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = handle_client_message(&text, &mut topics);
+                    send(&mut socket, &reply).await
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                // Ping/Pong отвечает axum, бинарные кадры игнорируем
+                Some(Ok(_)) => Ok(()),
+                Some(Err(e)) => {
+                    debug!("websocket receive error: {:?}", e);
+                    break;
+                }
+            },
+            event = events.recv() => match event {
+                Ok(event) if topics.contains(&event.topic) => {
+                    send(&mut socket, &ServerMessage::Event(&event)).await
+                }
+                Ok(_) => Ok(()),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    send(&mut socket, &ServerMessage::Lagged { skipped }).await
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        if sent.is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_client_message<'a>(text: &str, topics: &'a mut BTreeSet<String>) -> ServerMessage<'a> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            return ServerMessage::Error {
+                message: format!("invalid message: {}", e),
+            }
+        }
+    };
+
+    match message {
+        ClientMessage::Subscribe { topics: requested } => match Validator::validate_topics(&requested) {
+            Ok(requested) => {
+                topics.extend(requested);
+                ServerMessage::Subscribed { topics }
+            }
+            Err(e) => ServerMessage::Error { message: e.to_string() },
+        },
+        ClientMessage::Unsubscribe { topics: requested } => {
+            for topic in &requested {
+                topics.remove(topic);
+            }
+            ServerMessage::Subscribed { topics }
+        }
+        ClientMessage::Ping => ServerMessage::Pong,
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage<'_>) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text)).await
+}
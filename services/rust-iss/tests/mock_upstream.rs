@@ -0,0 +1,174 @@
+// Клиенты rust_iss против mock_upstream, поднятой в том же процессе на свободном порту.
+// Бинарник подключается как модуль, поэтому фича mock-upstream для тестов не нужна
+#[path = "../src/bin/mock_upstream.rs"]
+#[allow(dead_code)]
+mod mock_upstream;
+
+use std::sync::Once;
+
+use rust_iss::clients::{IssClient, NasaClient, SpaceXClient, TleClient};
+use rust_iss::config::Config;
+use rust_iss::domain::ApiError;
+use rust_iss::mapping::OsdrMapping;
+use rust_iss::orbit::{Sgp4, Tle};
+
+const KEY: &str = "TESTKEY";
+
+async fn start_mock(quota: i64) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = mock_upstream::router(mock_upstream::MockState::new(quota, 60, 2));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    base
+}
+
+// Клиентам база не нужна, но Config::from_env без DATABASE_URL не обходится
+fn config(base: &str) -> Config {
+    static ENV: Once = Once::new();
+    ENV.call_once(|| {
+        if std::env::var("DATABASE_URL").is_err() {
+            std::env::set_var("DATABASE_URL", "postgres://unused@127.0.0.1/unused");
+        }
+    });
+    let mut config = Config::from_env();
+    config.nasa_url = format!("{}/biodata/api/v2/datasets/?format=json", base);
+    config.nasa_api_base = base.to_string();
+    config.spacex_api_base = base.to_string();
+    config.where_iss_url = format!("{}/v1/satellites/25544", base);
+    config.tle_url = format!("{}/NORAD/elements/gp.php", base);
+    config.tle_file = None;
+    config.nasa_keys = vec![KEY.to_string()];
+    config.http_retries = 0;
+    config
+}
+
+#[tokio::test]
+async fn iss_position() {
+    let base = start_mock(1000).await;
+    let client = IssClient::new(config(&base)).unwrap();
+
+    let position = client.fetch_current().await.unwrap();
+    assert_eq!(position["id"], 25544);
+    assert!(position["latitude"].as_f64().is_some());
+    assert!(position["longitude"].as_f64().is_some());
+    // Заглушка подставляет текущее время
+    let age = chrono::Utc::now().timestamp() - position["timestamp"].as_i64().unwrap();
+    assert!(age.abs() < 60, "{}", age);
+}
+
+#[tokio::test]
+async fn apod_uses_key_and_reports_quota() {
+    let base = start_mock(2).await;
+    let client = NasaClient::new(config(&base)).unwrap();
+
+    let apod = client.fetch_apod().await.unwrap();
+    assert_eq!(apod["date"], "2024-05-12");
+    let quota = &client.keys.quotas()[0];
+    assert_eq!((quota.limit, quota.remaining, quota.requests), (Some(2), Some(1), 1));
+
+    client.fetch_apod().await.unwrap();
+    // Остаток квоты 0: единственный ключ отдыхает, запрос не уходит вовсе
+    let quota = &client.keys.quotas()[0];
+    assert_eq!((quota.remaining, quota.requests), (Some(0), 2));
+    assert!(quota.resting_until.is_some());
+    let err = client.fetch_apod().await.unwrap_err();
+    assert!(matches!(err, ApiError::RateLimited { .. }), "{:?}", err);
+    assert_eq!(client.keys.quotas()[0].requests, 2);
+}
+
+#[tokio::test]
+async fn neo_feed() {
+    let base = start_mock(1000).await;
+    let client = NasaClient::new(config(&base)).unwrap();
+
+    let feed = client.fetch_neo_feed("2024-05-10", "2024-05-11").await.unwrap();
+    assert_eq!(feed["element_count"], 3);
+    let days = feed["near_earth_objects"].as_object().unwrap();
+    let mut dates: Vec<&str> = days.keys().map(String::as_str).collect();
+    dates.sort();
+    assert_eq!(dates, ["2024-05-10", "2024-05-11"]);
+    let objects: usize = days.values().map(|d| d.as_array().unwrap().len()).sum();
+    assert_eq!(objects, 3);
+    for object in days.values().flat_map(|d| d.as_array().unwrap()) {
+        assert!(object["id"].is_string() && object["name"].is_string(), "{}", object);
+        assert!(object["close_approach_data"].as_array().is_some_and(|a| !a.is_empty()), "{}", object);
+    }
+    assert_eq!(client.keys.quotas()[0].requests, 1);
+}
+
+#[tokio::test]
+async fn donki_flares_and_cmes() {
+    let base = start_mock(1000).await;
+    let client = NasaClient::new(config(&base)).unwrap();
+
+    let flares = client.fetch_donki_flr("2024-05-10", "2024-05-11").await.unwrap();
+    let ids: Vec<&str> = flares.as_array().unwrap().iter().filter_map(|f| f["flrID"].as_str()).collect();
+    assert_eq!(
+        ids,
+        ["2024-05-10T06:27:00-FLR-001", "2024-05-11T01:10:00-FLR-001", "2024-05-11T11:15:00-FLR-001"]
+    );
+    assert!(flares[0]["classType"].is_string());
+
+    let cmes = client.fetch_donki_cme("2024-05-10", "2024-05-11").await.unwrap();
+    let ids: Vec<&str> = cmes.as_array().unwrap().iter().filter_map(|c| c["activityID"].as_str()).collect();
+    assert_eq!(ids, ["2024-05-10T07:12:00-CME-001", "2024-05-11T01:36:00-CME-001"]);
+    assert_eq!(client.keys.quotas()[0].requests, 2);
+}
+
+#[tokio::test]
+async fn spacex_next_launch() {
+    let base = start_mock(1000).await;
+    let client = SpaceXClient::new(config(&base)).unwrap();
+
+    let launch = client.fetch_next_launch().await.unwrap();
+    assert_eq!(launch["name"], "Crew-9");
+    assert_eq!(launch["upcoming"], true);
+    assert!(launch["date_utc"].is_string());
+}
+
+#[tokio::test]
+async fn tle_from_celestrak_path() {
+    let base = start_mock(1000).await;
+    let config = config(&base);
+    let url = config.tle_url.clone();
+    let client = TleClient::new(config).unwrap();
+
+    let (text, source) = client.fetch_tle().await.unwrap();
+    assert_eq!(source, url);
+    let tle = Tle::parse(&text).unwrap();
+    assert_eq!(tle.name.as_deref(), Some("ISS (ZARYA)"));
+    assert_eq!(tle.norad_id, 25544);
+    assert!(Sgp4::new(&tle).is_ok());
+}
+
+#[tokio::test]
+async fn osdr_listing_details_and_files() {
+    let base = start_mock(1000).await;
+    let client = NasaClient::new(config(&base)).unwrap();
+
+    // Три датасета по две заглушки на страницу
+    let entries = client.fetch_osdr_listing().await.unwrap();
+    let mut ids: Vec<String> = entries.iter().filter_map(|e| e.dataset_id.clone()).collect();
+    ids.sort();
+    assert_eq!(ids, ["OSD-120", "OSD-379", "OSD-48"]);
+
+    let targets = entries
+        .iter()
+        .map(|e| (e.dataset_id.clone().unwrap(), e.detail_url.clone().unwrap()))
+        .collect();
+    let mapping = OsdrMapping::builtin();
+    let details = client.fetch_osdr_details(targets).await;
+    assert_eq!(details.len(), 3);
+    let expected_files = [("OSD-48", 2), ("OSD-379", 1), ("OSD-120", 0)];
+    for (id, detail) in details {
+        let detail = detail.unwrap();
+        let record = mapping.apply(detail.clone());
+        assert_eq!(record.dataset_id.as_deref(), Some(id.as_str()));
+        assert!(record.title.is_some(), "{}", id);
+
+        let files_url = detail.pointer("/files/REST_URL").and_then(|v| v.as_str()).unwrap();
+        let files = client.fetch_osdr_files(&id, files_url).await.unwrap();
+        let count = expected_files.iter().find(|(i, _)| *i == id).unwrap().1;
+        assert_eq!(files.as_object().map(|f| f.len()), Some(count), "{}: {}", id, files);
+    }
+}